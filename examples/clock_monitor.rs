@@ -0,0 +1,61 @@
+//! HSE clock monitor example for the HT32F1755
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+
+use cortex_m::interrupt::Mutex;
+use defmt_rtt as _;
+use ht32f1yyy_hal as hal;
+use panic_probe as _;
+
+use hal::ckcu::{CkcuExt, ClockMonitor, Clocks};
+use hal::pac::{self, interrupt};
+use hal::time::RateExtU32;
+
+static MONITOR: Mutex<RefCell<Option<ClockMonitor>>> = Mutex::new(RefCell::new(None));
+static CLOCKS: Mutex<RefCell<Option<Clocks>>> = Mutex::new(RefCell::new(None));
+
+#[cortex_m_rt::entry]
+fn main() -> ! {
+    defmt::info!("Example: Clock monitor");
+    let dp = pac::Peripherals::take().unwrap();
+    let ckcu = dp.CKCU.constrain(dp.RSTCU);
+
+    let clocks = ckcu.configuration
+        .use_hse(8.MHz())
+        .ck_sys(72u32.MHz())
+        .enable_clock_monitor()
+        .freeze();
+
+    let mut monitor = ckcu.clock_monitor;
+    monitor.listen();
+
+    cortex_m::interrupt::free(|cs| {
+        MONITOR.borrow(cs).replace(Some(monitor));
+        CLOCKS.borrow(cs).replace(Some(clocks));
+    });
+    unsafe { cortex_m::peripheral::NVIC::unmask(interrupt::CKRDY) };
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+#[interrupt]
+fn CKRDY() {
+    cortex_m::interrupt::free(|cs| {
+        let mut monitor = MONITOR.borrow(cs).borrow_mut();
+        let mut clocks = CLOCKS.borrow(cs).borrow_mut();
+        if let (Some(monitor), Some(old)) = (monitor.as_mut(), clocks.take()) {
+            if monitor.has_failed() {
+                let new = monitor.recover(old);
+                // Drivers set up with the old clocks have to be set up again
+                defmt::warn!("HSE failed, HCLK is now {} Hz", new.hclk().raw());
+                *clocks = Some(new);
+            } else {
+                *clocks = Some(old);
+            }
+        }
+    });
+}
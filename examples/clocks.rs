@@ -7,6 +7,8 @@ use ht32f1yyy_hal as hal;
 use panic_probe as _;
 
 use hal::ckcu::CkcuExt;
+use hal::delay::SysDelay;
+use hal::hal::delay::DelayNs;
 use hal::pac;
 use hal::time::RateExtU32;
 
@@ -17,7 +19,7 @@ fn main() -> ! {
     let dp = pac::Peripherals::take().unwrap();
     let ckcu = dp.CKCU.constrain(dp.RSTCU);
 
-    let _clocks = ckcu.configuration
+    let clocks = ckcu.configuration
         .use_hse(8.MHz())
         .ck_sys(144u32.MHz())
         .hclk(72u32.MHz())
@@ -26,16 +28,10 @@ fn main() -> ! {
 
     defmt::info!("Example: CKCU, done");
 
-    let mut syst = cp.SYST;
-    // SysTick happens every 8 core clock cycles
-    // reload value = 72 MHz / 8 -> 1 Hz wrap
-    syst.set_reload(9_000_000);
-    syst.clear_current();
-    syst.enable_counter();
+    let mut delay = SysDelay::new(cp.SYST, &clocks);
 
     loop {
-        if syst.has_wrapped() {
-            defmt::info!("Tick");
-        }
+        delay.delay_ms(1_000);
+        defmt::info!("Tick");
     }
 }
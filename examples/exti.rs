@@ -0,0 +1,64 @@
+//! EXTI example for the HT32F1755
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+
+use cortex_m::interrupt::Mutex;
+use defmt_rtt as _;
+use ht32f1yyy_hal as hal;
+use panic_probe as _;
+
+use hal::ckcu::CkcuExt;
+use hal::gpio::gpioc::PC11;
+use hal::gpio::{Afio, Edge, Exti, ExtiPin, GpioExt, Input, PullDown, AF0};
+use hal::pac::{self, interrupt};
+use hal::time::{MicroSeconds, RateExtU32};
+
+static BUTTON: Mutex<RefCell<Option<PC11<Input<PullDown>, AF0>>>> = Mutex::new(RefCell::new(None));
+
+#[cortex_m_rt::entry]
+fn main() -> ! {
+    defmt::info!("Example: EXTI");
+    let dp = pac::Peripherals::take().unwrap();
+    let ckcu = dp.CKCU.constrain(dp.RSTCU);
+    let mut afio = Afio::new(dp.AFIO);
+    let mut exti = Exti::new(dp.EXTI);
+
+    let clocks = ckcu.configuration
+        .use_hse(8.MHz())
+        .ck_sys(144u32.MHz())
+        .hclk(72u32.MHz())
+        .ck_usb(48u32.MHz())
+        .freeze();
+
+    let gpioc = dp.GPIOC.split();
+
+    let mut button = gpioc.pc11.into_input_pull_down();
+    button.make_interrupt_source(&mut afio);
+    button.trigger_on_edge(&mut exti, Edge::Rising);
+    // Let the hardware filter out the contact bounce
+    button
+        .channel()
+        .set_debounce(&mut exti, MicroSeconds::from_ticks(10_000), &clocks)
+        .unwrap();
+    button.enable_interrupt(&mut exti);
+
+    cortex_m::interrupt::free(|cs| BUTTON.borrow(cs).replace(Some(button)));
+    unsafe { cortex_m::peripheral::NVIC::unmask(interrupt::EXTI11) };
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+#[interrupt]
+fn EXTI11() {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(button) = BUTTON.borrow(cs).borrow_mut().as_mut() {
+            button.clear_interrupt_pending_bit();
+        }
+    });
+
+    defmt::info!("Button pressed");
+}
@@ -1,6 +1,7 @@
 //! Clock Control Unit + Reset Control Unit
-use crate::pac::{CKCU, FMC, RSTCU};
-use crate::time::{Hertz, RateExtU32};
+use crate::gpio::Afio;
+use crate::pac::{CKCU, FMC, RSTCU, RTC};
+use crate::time::{Hertz, MilliSeconds, RateExtU32};
 
 /// Extension trait that constrains the `Ckcu` peripheral
 pub trait CkcuExt {
@@ -10,18 +11,23 @@ pub trait CkcuExt {
 
 impl CkcuExt for CKCU {
     // Also take RSTCU here so it is impossible to safely generate resets for
-    // peripherals
+    // peripherals, other than through `PeripheralControl`
     fn constrain(self, _rstcu: RSTCU) -> Ckcu {
         Ckcu {
             configuration: Configuration {
                 ckout: None,
                 hse: None,
                 lse: None,
+                lse_drive: LseDrive::Normal,
+                lse_timeout: MilliSeconds::from_ticks(LSE_TIMEOUT_MS),
                 ck_usb: None,
                 ck_adc_ip: None,
                 hclk: None,
                 ck_sys: None,
+                clock_monitor: false,
             },
+            clock_monitor: ClockMonitor { _private: () },
+            peripherals: PeripheralControl { _private: () },
         }
     }
 }
@@ -29,16 +35,43 @@ impl CkcuExt for CKCU {
 /// Constrained Ckcu peripheral
 pub struct Ckcu {
     pub configuration: Configuration,
+    pub clock_monitor: ClockMonitor,
+    pub peripherals: PeripheralControl,
 }
 
 /// High Speed Internal Oscillator at 8 Mhz
 const HSI: u32 = 8_000_000;
 /// Low Speed Internal Oscillator at 32 Khz
 const LSI: u32 = 32_000;
+/// Default time to wait for the LSE to start up, crystals can take a
+/// couple of seconds on a cold start
+const LSE_TIMEOUT_MS: u32 = 2_000;
+
+// Frequency limits, refer to CKCU Block Diagram in User Manual
+/// Maximum frequency for CK_SYS
+const CK_SYS_MAX: u32 = 144_000_000;
+/// Maximum frequency for CK_USB
+const CK_USB_MAX: u32 = 48_000_000;
+/// Maximum frequency for HCLK
+const HCLK_MAX: u32 = 72_000_000;
+/// Minimum frequency for the PLL VCO output
+const VCO_MIN: u32 = 64_000_000;
+/// Maximum frequency for the PLL VCO output
+const VCO_MAX: u32 = 144_000_000;
+/// Minimum frequency for the PLL output
+const PLL_OUT_MIN: u32 = 8_000_000;
+/// Maximum frequency for the PLL output
+const PLL_OUT_MAX: u32 = 144_000_000;
+/// How far CK_PLL may be off the requested CK_SYS, in percent.
+///
+/// CK_PLL = CK_in * (NF2 / NO2) can not hit every frequency, but anything
+/// further off than this would skew every baud rate and timer derived from
+/// it, so `ClockError::PllUnreachable` is returned instead.
+const PLL_MAX_DEVIATION_PERCENT: u32 = 1;
 
 /// All clocks that can be outputted via CKOUT.
 /// See User Manual page 91.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CkoutSrc {
     /// Output the CK_REF, no prescaler
     CkRef,
@@ -56,6 +89,21 @@ pub enum CkoutSrc {
     CkLsi,
 }
 
+impl CkoutSrc {
+    /// The CKOUTSRC register value for this source
+    fn bits(self) -> u8 {
+        match self {
+            CkoutSrc::CkRef => 0b000,
+            CkoutSrc::Hclk => 0b001,
+            CkoutSrc::CkSys => 0b010,
+            CkoutSrc::CkHse => 0b011,
+            CkoutSrc::CkHsi => 0b100,
+            CkoutSrc::CkLse => 0b101,
+            CkoutSrc::CkLsi => 0b110,
+        }
+    }
+}
+
 /// Representation of the HT32F52342 clock tree.
 ///
 /// Note that this struct only represents the targeted values.
@@ -68,8 +116,12 @@ pub struct Configuration {
     ckout: Option<CkoutSrc>,
     /// The frequency of an HSE, should one be given
     hse: Option<Hertz>,
-    /// The frequency of an LSI, should one be given.
+    /// The frequency of an LSE, should one be given.
     lse: Option<Hertz>,
+    /// The startup mode for the LSE
+    lse_drive: LseDrive,
+    /// How long to wait for the LSE to become ready
+    lse_timeout: MilliSeconds,
     /// The optimal frequency for CK_USB, aka the USB clock
     ck_usb: Option<Hertz>,
     /// The optimal frequency for CK_ADC_IP, aka the ADC clock
@@ -78,13 +130,16 @@ pub struct Configuration {
     ck_sys: Option<Hertz>,
     /// The optimal frequency for HCLK, aka the AHB bus
     hclk: Option<Hertz>,
+    /// Whether the HSE should be monitored for failures
+    clock_monitor: bool,
 }
 
 /// Frozen core clock frequencies
 ///
 /// The existence of this value indicates that the core clock
-/// configuration can no longer be changed.
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// configuration can no longer be changed, other than through
+/// [`Clocks::reconfigure`] which consumes it.
+#[derive(Debug, PartialEq)]
 pub struct Clocks {
     /// Which clock should be outputted via CKOUT, if any
     pub(crate) ckout: Option<CkoutSrc>,
@@ -98,6 +153,315 @@ pub struct Clocks {
     pub(crate) stclk: Hertz,
     /// The frequency for HCLK, aka the AHB bus
     pub(crate) hclk: Hertz,
+    /// The clock driving CK_SYS
+    pub(crate) sys_src: SysClkSrc,
+    /// The PLL configuration, if the PLL is running
+    pub(crate) pll: Option<PllConfig>,
+    /// The number of flash wait states
+    pub(crate) wait_states: u8,
+    /// The AHB prescaler
+    pub(crate) ahb_div: u8,
+    /// The ADC prescaler
+    pub(crate) adc_div: u8,
+    /// The clock driving the RTC
+    pub(crate) rtc_src: RtcSrc,
+    /// The frequency of the LSE, if it is running and known
+    pub(crate) lse: Option<Hertz>,
+    /// The frequency of CK_REF, if the PLL is running
+    #[cfg(any(
+        feature = "ht32f1653",
+        feature = "ht32f1654",
+        feature = "ht32f1655",
+        feature = "ht32f1656",
+    ))]
+    pub(crate) ck_ref: Option<Hertz>,
+    /// The frequency of the HSE, if it is running
+    pub(crate) hse: Option<Hertz>,
+}
+
+impl Clocks {
+    /// Move the running clock tree over to a new, already validated,
+    /// clock configuration.
+    ///
+    /// These `Clocks` are consumed, as every frequency derived from them
+    /// is stale afterwards. Nothing keeps drivers from running on with the
+    /// frequencies they derived from the old `Clocks` at setup, so every
+    /// peripheral, delay and timer has to be set up again with the
+    /// returned `Clocks`.
+    ///
+    /// Flash wait states are raised before and lowered after the switch,
+    /// CK_SYS is parked on the HSI while the PLL is reconfigured and the
+    /// PLL is disabled when nothing runs from it anymore.
+    ///
+    /// The low speed oscillators and the RTC source are left as they are,
+    /// the LSE settings of `plan` are ignored.
+    pub fn reconfigure(self, plan: ClockPlan) -> Clocks {
+        plan.apply(Some((self.rtc_src, self.lse)))
+    }
+
+    /// Returns the clock that is outputted via CKOUT, if any
+    pub fn ckout(&self) -> Option<CkoutSrc> {
+        self.ckout
+    }
+
+    /// Returns the frequency of CK_USB, aka the USB clock
+    pub fn ck_usb(&self) -> Hertz {
+        self.ck_usb
+    }
+
+    /// Returns the frequency of CK_ADC_IP, aka the ADC clock
+    pub fn ck_adc_ip(&self) -> Hertz {
+        self.ck_adc_ip
+    }
+
+    /// Returns the frequency of CK_SYS
+    pub fn ck_sys(&self) -> Hertz {
+        self.ck_sys
+    }
+
+    /// Returns the frequency of STCLK, aka the SysTick clock
+    pub fn stclk(&self) -> Hertz {
+        self.stclk
+    }
+
+    /// Returns the frequency of HCLK, aka the AHB bus
+    pub fn hclk(&self) -> Hertz {
+        self.hclk
+    }
+
+    /// Returns the frequency of PCLK, aka the APB bus
+    ///
+    /// For HT32F1yyy PCLK = HCLK
+    pub fn pclk(&self) -> Hertz {
+        self.hclk
+    }
+
+    /// Returns the frequency of CK_PLL, if the PLL is running
+    pub fn ck_pll(&self) -> Option<Hertz> {
+        self.pll.map(|pll| pll.ck_pll)
+    }
+
+    /// Returns the clock driving the PLL, if the PLL is running
+    pub fn pll_src(&self) -> Option<PllSrc> {
+        self.pll.map(|pll| pll.src)
+    }
+
+    /// Returns the clock driving CK_SYS
+    pub fn sys_src(&self) -> SysClkSrc {
+        self.sys_src
+    }
+
+    /// Returns the number of flash wait states
+    pub fn wait_states(&self) -> u8 {
+        self.wait_states
+    }
+
+    /// Returns the clock driving the RTC
+    pub fn rtc_src(&self) -> RtcSrc {
+        self.rtc_src
+    }
+
+    /// Returns the frequency of CK_RTC, aka the RTC clock
+    ///
+    /// Returns `None` if the RTC was left running from an LSE that was not
+    /// passed to [`Configuration::use_lse`], as its frequency is unknown.
+    pub fn ck_rtc(&self) -> Option<Hertz> {
+        match self.rtc_src {
+            RtcSrc::Lse => self.lse,
+            RtcSrc::Lsi => Some(LSI.Hz()),
+        }
+    }
+
+    /// Returns the frequency of CK_REF, if the PLL is running
+    ///
+    /// CK_REF = CK_PLL / ((CKREFPRE + 1) * 2)
+    #[cfg(any(
+        feature = "ht32f1653",
+        feature = "ht32f1654",
+        feature = "ht32f1655",
+        feature = "ht32f1656",
+    ))]
+    pub fn ck_ref(&self) -> Option<Hertz> {
+        self.ck_ref
+    }
+
+    /// Returns the frequency of CK_LSE, if the LSE started up through
+    /// [`Configuration::use_lse`]
+    pub fn ck_lse(&self) -> Option<Hertz> {
+        self.lse
+    }
+
+    /// Returns the frequency of CK_LSI
+    pub fn ck_lsi(&self) -> Hertz {
+        LSI.Hz()
+    }
+
+    /// Returns the frequency of CK_HSE, if the HSE is running
+    pub fn ck_hse(&self) -> Option<Hertz> {
+        self.hse
+    }
+
+    /// Returns the frequency of CK_HSI
+    pub fn ck_hsi(&self) -> Hertz {
+        HSI.Hz()
+    }
+
+    /// Returns the frequency on the CKOUT pin for the clock that is
+    /// outputted via CKOUT, if any
+    pub fn ck_out(&self) -> Option<Hertz> {
+        self.ckout.and_then(|src| self.ckout_frequency(src))
+    }
+
+    /// Returns the frequency on the CKOUT pin for the given source.
+    ///
+    /// HCLK, CK_SYS, CK_HSE and CK_HSI are divided by 16 before being
+    /// outputted. Returns `None` if the source is not running, and for
+    /// CK_REF on devices without a CK_REF prescaler (CKREFPRE), where its
+    /// frequency is not known.
+    pub fn ckout_frequency(&self, src: CkoutSrc) -> Option<Hertz> {
+        match src {
+            #[cfg(any(
+                feature = "ht32f1653",
+                feature = "ht32f1654",
+                feature = "ht32f1655",
+                feature = "ht32f1656",
+            ))]
+            CkoutSrc::CkRef => self.ck_ref,
+            #[cfg(not(any(
+                feature = "ht32f1653",
+                feature = "ht32f1654",
+                feature = "ht32f1655",
+                feature = "ht32f1656",
+            )))]
+            CkoutSrc::CkRef => None,
+            CkoutSrc::Hclk => Some(self.hclk / 16),
+            CkoutSrc::CkSys => Some(self.ck_sys / 16),
+            CkoutSrc::CkHse => self.hse.map(|hse| hse / 16),
+            CkoutSrc::CkHsi => Some(HSI.Hz::<1, 1>() / 16),
+            CkoutSrc::CkLse => self.lse,
+            CkoutSrc::CkLsi => Some(LSI.Hz()),
+        }
+    }
+}
+
+/// Errors that can occur while solving the clock tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockError {
+    /// The requested CK_SYS is above 144 Mhz
+    CkSysTooHigh,
+    /// The requested CK_USB is above 48 Mhz
+    CkUsbTooHigh,
+    /// The resulting HCLK is above 72 Mhz
+    ///
+    /// Such a configuration used to be applied as is, running the AHB out
+    /// of spec. Lower HCLK with [`Configuration::hclk`] to get the AHB
+    /// prescaler involved.
+    HclkTooHigh,
+    /// The PLL can not get within 1% of its target frequency
+    PllUnreachable,
+    /// The requested HCLK can not be derived from CK_SYS
+    HclkUnreachable,
+    /// The requested CK_USB can not be derived exactly from CK_PLL
+    CkUsbUnreachable,
+    /// The requested CK_ADC_IP can not be derived from HCLK
+    CkAdcUnreachable,
+    /// The clock monitor was enabled without an HSE to monitor
+    ClockMonitorWithoutHse,
+}
+
+/// All clocks that can drive CK_SYS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SysClkSrc {
+    /// The PLL output, CK_PLL
+    Pll,
+    /// The High Speed External oscillator
+    Hse,
+    /// The High Speed Internal oscillator
+    Hsi,
+}
+
+/// All clocks that can drive the PLL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PllSrc {
+    /// The High Speed External oscillator
+    Hse,
+    /// The High Speed Internal oscillator
+    Hsi,
+}
+
+/// All clocks that can drive the RTC, aka CK_RTC
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RtcSrc {
+    /// The Low Speed Internal oscillator
+    Lsi,
+    /// The Low Speed External oscillator
+    Lse,
+}
+
+/// Startup modes for the LSE oscillator, aka the LSESM bit in RTC_CR
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LseDrive {
+    /// Normal drive, lowest power consumption
+    Normal,
+    /// High drive, the crystal starts up faster at the cost of
+    /// a higher power consumption
+    Fast,
+}
+
+/// PLL settings, CK_PLL = CK_in * (NF2 / NO2)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PllConfig {
+    src: PllSrc,
+    nf2: u8,
+    no2: u8,
+    ck_pll: Hertz,
+}
+
+impl PllConfig {
+    /// The clock driving the PLL
+    pub fn src(&self) -> PllSrc {
+        self.src
+    }
+
+    /// The feedback divider, 1 to 64
+    pub fn nf2(&self) -> u8 {
+        self.nf2
+    }
+
+    /// The output divider, 1, 2, 4 or 8
+    pub fn no2(&self) -> u8 {
+        self.no2
+    }
+
+    /// The resulting frequency for CK_PLL
+    pub fn ck_pll(&self) -> Hertz {
+        self.ck_pll
+    }
+}
+
+/// A solved clock tree, as calculated by [`Configuration::plan`]
+///
+/// This does not touch any registers, so it can be used to check
+/// a configuration before applying it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockPlan {
+    sys_src: SysClkSrc,
+    pll: Option<PllConfig>,
+    ahb_div: u8,
+    usb_div: u8,
+    adc_div: u8,
+    wait_states: u8,
+    clock_monitor: bool,
+    ckout: Option<CkoutSrc>,
+    hse: Option<Hertz>,
+    lse: Option<Hertz>,
+    lse_drive: LseDrive,
+    lse_timeout: MilliSeconds,
+    ck_usb: Hertz,
+    ck_adc_ip: Hertz,
+    ck_sys: Hertz,
+    stclk: Hertz,
+    hclk: Hertz,
 }
 
 impl Configuration {
@@ -123,6 +487,9 @@ impl Configuration {
     /// will make it prefer the LSE over the LSI in case the LSI should
     /// turn out to be the fitting clock for a certain part of the
     /// configuration.
+    ///
+    /// The LSE will then drive the RTC, should it not start up in time
+    /// the LSI is used instead, see [`lse_timeout`](Self::lse_timeout).
     pub fn use_lse<F>(mut self, freq: F) -> Self
     where
         F: Into<Hertz>,
@@ -131,6 +498,19 @@ impl Configuration {
         self
     }
 
+    /// Sets the startup mode for the LSE
+    pub fn lse_drive(mut self, drive: LseDrive) -> Self {
+        self.lse_drive = drive;
+        self
+    }
+
+    /// Sets how long to wait for the LSE to become ready before
+    /// falling back on the LSI, defaults to 2 seconds
+    pub fn lse_timeout(mut self, timeout: MilliSeconds) -> Self {
+        self.lse_timeout = timeout;
+        self
+    }
+
     /// Sets the desired value for CK_USB
     pub fn ck_usb<F>(mut self, freq: F) -> Self
     where
@@ -167,142 +547,121 @@ impl Configuration {
         self
     }
 
-    /// Freeze the configuration into a Clocks struct and apply it
-    pub fn freeze(self) -> Clocks {
+    /// Enable the clock monitor, which switches CK_SYS back to the HSI
+    /// should the HSE fail. See [`ClockMonitor`] on how to get notified.
+    ///
+    /// Requires an HSE, see [`use_hse`](Self::use_hse).
+    pub fn enable_clock_monitor(mut self) -> Self {
+        self.clock_monitor = true;
+        self
+    }
+
+    /// Calculate how the clock tree should be set up, without touching any
+    /// registers.
+    ///
+    /// This is the part of [`freeze`](Self::freeze) that solves the clock
+    /// tree, an error is returned if the targeted values can not be achieved.
+    ///
+    /// Note that a CK_SYS above 72 Mhz now requires an explicit
+    /// [`hclk`](Self::hclk) target, otherwise HCLK would exceed its maximum
+    /// and [`ClockError::HclkTooHigh`] is returned.
+    pub fn plan(&self) -> Result<ClockPlan, ClockError> {
+        // There is nothing for the clock monitor to watch without an HSE
+        if self.clock_monitor && self.hse.is_none() {
+            return Err(ClockError::ClockMonitorWithoutHse);
+        }
+
         // High speed oscillator
         let hso = self.hse.unwrap_or_else(|| HSI.Hz());
         // PLL source clock, see top left corner of the clock tree
-        let pllsrc = self.hse.is_none();
+        let pll_src = match self.hse {
+            Some(_) => PllSrc::Hse,
+            None => PllSrc::Hsi,
+        };
 
         let mut pll_target_clock = None;
 
-        // Refer to User manual for SW values
-        let (sw, mut ck_sys) = match self.ck_sys {
+        let (sys_src, mut ck_sys) = match self.ck_sys {
             Some(ck_sys) => {
                 // Maximum frequency for CK_SYS is 144 Mhz
                 // Refer to CKCU Block Diagram in User Manual
-                assert!(ck_sys <= 144.MHz::<1, 1>());
+                if ck_sys.raw() > CK_SYS_MAX {
+                    return Err(ClockError::CkSysTooHigh);
+                }
 
                 if self.hse.map(|h| h == ck_sys).unwrap_or(false) {
-                    (0b10, self.hse.unwrap())
+                    (SysClkSrc::Hse, ck_sys)
                 } else if ck_sys.raw() == HSI {
-                    (0b11, HSI.Hz())
+                    (SysClkSrc::Hsi, ck_sys)
                 }
                 // If no exact match is found, use the pll
                 else {
                     pll_target_clock = Some(ck_sys);
-                    (0b00, ck_sys)
+                    (SysClkSrc::Pll, ck_sys)
                 }
             }
             // If no value is given select the high speed oscillator,
             // furthermore automatically choose HSE if it's provided.
             None => match self.hse {
-                Some(hse) => (0b10, hse),
-                None => (0b11, HSI.Hz()),
+                Some(hse) => (SysClkSrc::Hse, hse),
+                None => (SysClkSrc::Hsi, HSI.Hz()),
             },
         };
 
-        match self.ck_usb {
-            Some(ck_usb) => {
-                // Maximum frequency for CK_USB is 48 Mhz
-                // Refer to CKCU Block Diagram in User Manual
-                assert!(ck_usb <= 48.MHz::<1, 1>());
-                if pll_target_clock.is_none() {
-                    pll_target_clock = self.ck_usb;
-                }
-                ck_usb
-            }
-            None => 0.Hz(),
+        let usb_target = match self.ck_usb {
+            // Maximum frequency for CK_USB is 48 Mhz
+            // Refer to CKCU Block Diagram in User Manual
+            Some(ck_usb) if ck_usb.raw() > CK_USB_MAX => return Err(ClockError::CkUsbTooHigh),
+            Some(ck_usb) if ck_usb.raw() == 0 => return Err(ClockError::CkUsbUnreachable),
+            Some(ck_usb) => Some(ck_usb.raw()),
+            None => None,
         };
 
-        let (mut nf2, mut no2) = (None, None);
-        if let Some(pll_target) = pll_target_clock {
-            // According to User Manual: pll_out = CK_in (NF2/NO2)
-            let optimal_divider = pll_target.raw() as f32 / hso.raw() as f32;
-            let mut closest = (1, 1);
-            let mut difference = f32::MAX;
-
-            // Try all combinations of NF2 and NO2, there are only
-            // 256 so this should be fine.
-            for nf2 in 1..=64 {
-                // VCO_out = CK_in * NF2
-                // and VCO_out must be between 64 and 144 Mhz
-                let vco_out = hso.raw() * nf2;
-                if (64_000_000..=144_000_000).contains(&vco_out) {
-                    for no2 in &[1, 2, 4, 8] {
-                        let current_divider = nf2 as f32 / *no2 as f32;
-
-                        // The maximum output frequency for the PLL must be
-                        // between 8 and 144 Mhz
-                        let current_output = current_divider * hso.raw() as f32;
-                        if !(8_000_000.0..=144_000_000.0).contains(&current_output) {
-                            continue;
-                        }
-
-                        let mut current_difference = optimal_divider - current_divider;
-                        if current_difference < 0.0 {
-                            current_difference *= -1.0
-                        }
-
-                        if current_difference < difference {
-                            closest = (nf2 as u8, *no2);
-                            difference = current_difference;
-                        }
-                    }
-                }
-            }
-
-            let ck_pll = ((hso.raw() as f32 * (closest.0 as f32 / closest.1 as f32)) as u32).Hz();
-            if sw == 0b00 {
-                ck_sys = ck_pll;
+        // CK_USB is always derived from CK_PLL, so the PLL has to run if
+        // either CK_SYS or CK_USB needs it
+        let (pll, usb_div) = if pll_target_clock.is_some() || usb_target.is_some() {
+            let (nf2, no2, ck_pll, usb_div) = pll_search(
+                hso.raw(),
+                pll_target_clock.map(|target| target.raw()),
+                usb_target,
+            )?;
+            if sys_src == SysClkSrc::Pll {
+                ck_sys = ck_pll.Hz();
             }
 
-            // Map NF2 values to their respective register values
-            closest.0 = if closest.0 == 64 { 0 } else { closest.0 };
-
-            // Map NO2 values to their respective register values
-            // Refer to User manual page 88
-            closest.1 = match closest.1 {
-                1 => 0b00,
-                2 => 0b01,
-                4 => 0b10,
-                8 => 0b11,
-                _ => unreachable!(),
+            let pll = PllConfig {
+                src: pll_src,
+                nf2,
+                no2,
+                ck_pll: ck_pll.Hz(),
             };
-
-            nf2 = Some(closest.0);
-            no2 = Some(closest.1);
-        }
+            (Some(pll), usb_div)
+        } else {
+            (None, None)
+        };
 
         // Calculate the AHB clock prescaler
         // hclk = ck_sys / ahb prescaler
-        let (ahb_div, hclk) = match self.hclk {
-            Some(hclk) => {
-                let (bits, div) = match ck_sys.raw() / hclk.raw() {
-                    0 => unreachable!(),
-                    1 => (0b00, 1),
-                    2..=3 => (0b01, 2),
-                    4..=7 => (0b10, 4),
-                    _ => (0b11, 8),
-                };
-
-                (bits, (ck_sys.raw() / div).Hz())
-            }
-            None => (0b000, ck_sys),
+        let ahb_div = match self.hclk {
+            Some(hclk) if hclk.raw() == 0 => return Err(ClockError::HclkUnreachable),
+            Some(hclk) => match ck_sys.raw() / hclk.raw() {
+                0 => return Err(ClockError::HclkUnreachable),
+                1 => 1,
+                2..=3 => 2,
+                4..=7 => 4,
+                _ => 8,
+            },
+            None => 1,
         };
+        let hclk = (ck_sys.raw() / ahb_div as u32).Hz::<1, 1>();
+        if hclk.raw() > HCLK_MAX {
+            return Err(ClockError::HclkTooHigh);
+        }
 
-        let (usb_div, ck_usb) = match self.ck_usb {
-            Some(usbclk) => {
-                // TODO: this should be ck_pll, since there is no guarantuee ck_sys == ck_pll
-                let (bits, div) = match ck_sys.raw() / usbclk.raw() {
-                    0 => unreachable!(),
-                    1 => (0b00, 1),
-                    2 => (0b01, 2),
-                    _ => (0b10, 3),
-                };
-                (bits, (ck_sys.raw() / div).Hz())
-            }
-            None => (0b10, 0.Hz()),
+        // ck_usb = ck_pll / usb prescaler
+        let (usb_div, ck_usb) = match (pll, usb_div) {
+            (Some(pll), Some(div)) => (div, (pll.ck_pll.raw() / div as u32).Hz()),
+            _ => (3, 0.Hz()),
         };
 
         // SysTick clock
@@ -310,43 +669,322 @@ impl Configuration {
 
         // Calculate the ADC clock prescaler
         // ck_adc_ip = hclk / adc prescaler
-        let (adc_div, ck_adc_ip) = match self.ck_adc_ip {
-            Some(ck_adc_ip) => {
-                let (bits, div) = match hclk.raw() / ck_adc_ip.raw() {
-                    0 => unreachable!(),
-                    1 => (0b000, 1),
-                    2..=3 => (0b001, 2),
-                    4..=5 => (0b010, 4),
-                    6..=7 => (0b111, 6),
-                    8..=15 => (0b011, 8),
-                    16..=31 => (0b100, 16),
-                    32..=63 => (0b101, 32),
-                    _ => (0b110, 64),
-                };
-
-                (bits, (hclk.raw() / div).Hz())
-            }
-            None => (0b000, hclk),
+        let adc_div = match self.ck_adc_ip {
+            Some(ck_adc_ip) if ck_adc_ip.raw() == 0 => return Err(ClockError::CkAdcUnreachable),
+            Some(ck_adc_ip) => match hclk.raw() / ck_adc_ip.raw() {
+                0 => return Err(ClockError::CkAdcUnreachable),
+                1 => 1,
+                2..=3 => 2,
+                4..=5 => 4,
+                6..=7 => 6,
+                8..=15 => 8,
+                16..=31 => 16,
+                32..=63 => 32,
+                _ => 64,
+            },
+            None => 1,
         };
+        let ck_adc_ip = (hclk.raw() / adc_div as u32).Hz();
+
+        // The flash needs more wait states on higher frequencies,
+        // otherwise the chip hangs
+        let wait_states = if hclk.raw() > 48_000_000 {
+            2
+        } else if hclk.raw() > 24_000_000 {
+            1
+        } else {
+            0
+        };
+
+        Ok(ClockPlan {
+            sys_src,
+            pll,
+            ahb_div,
+            usb_div,
+            adc_div,
+            wait_states,
+            clock_monitor: self.clock_monitor,
+            ckout: self.ckout,
+            hse: self.hse,
+            lse: self.lse,
+            lse_drive: self.lse_drive,
+            lse_timeout: self.lse_timeout,
+            ck_usb,
+            ck_adc_ip,
+            ck_sys,
+            stclk,
+            hclk,
+        })
+    }
+
+    /// Freeze the configuration into a Clocks struct and apply it
+    ///
+    /// Returns an error, without touching any registers, if the
+    /// configuration can not be achieved.
+    pub fn try_freeze(self) -> Result<Clocks, ClockError> {
+        let plan = self.plan()?;
+        let clocks = plan.apply(None);
+
+        // Reset AFIO here because the GPIO implementation is block wise ->
+        // Resetting AFIO during GPIO initialization could lead to already being
+        // used pins / their AF being reset.
+        (unsafe { &*RSTCU::ptr() })
+            .rstcu_apbprstr0
+            .modify(|_, w| w.afiorst().set_bit());
+
+        Ok(clocks)
+    }
+
+    /// Freeze the configuration into a Clocks struct and apply it
+    ///
+    /// # Panics
+    ///
+    /// Panics if the configuration can not be achieved, see
+    /// [`try_freeze`](Self::try_freeze) for a fallible version.
+    pub fn freeze(self) -> Clocks {
+        self.try_freeze().unwrap()
+    }
+}
+
+/// Search for the NF2 and NO2 values that get the PLL output closest to
+/// `sys_target`, starting from a `ck_in` input clock.
+///
+/// If `usb_target` is given, only PLL outputs that can be divided down
+/// to exactly `usb_target` by the USB prescaler are considered.
+///
+/// Returns `(NF2, NO2, CK_PLL, USB prescaler)`.
+fn pll_search(
+    ck_in: u32,
+    sys_target: Option<u32>,
+    usb_target: Option<u32>,
+) -> Result<(u8, u8, u32, Option<u8>), ClockError> {
+    let mut closest = None;
+    let mut difference = u32::MAX;
+
+    // Try all combinations of NF2 and NO2, there are only
+    // 256 so this should be fine.
+    for nf2 in 1..=64u32 {
+        // VCO_out = CK_in * NF2
+        // and VCO_out must be between 64 and 144 Mhz
+        let vco_out = ck_in as u64 * nf2 as u64;
+        if !(VCO_MIN as u64..=VCO_MAX as u64).contains(&vco_out) {
+            continue;
+        }
+
+        for no2 in [1, 2, 4, 8] {
+            // According to User Manual: pll_out = CK_in (NF2/NO2)
+            // The output frequency for the PLL must be between 8 and 144 Mhz
+            let current_output = (vco_out / no2 as u64) as u32;
+            if !(PLL_OUT_MIN..=PLL_OUT_MAX).contains(&current_output) {
+                continue;
+            }
+
+            // USB needs an exact clock, so look for a prescaler that gets
+            // CK_PLL / prescaler == CK_USB without any remainder
+            let usb_div = match usb_target {
+                Some(usb) => {
+                    match (1..=3u8).find(|div| vco_out == usb as u64 * *div as u64 * no2 as u64) {
+                        Some(div) => Some(div),
+                        None => continue,
+                    }
+                }
+                None => None,
+            };
+
+            // Without a target for CK_SYS, the first output that fits CK_USB will do
+            let current_difference = match sys_target {
+                Some(target) => current_output.abs_diff(target),
+                None => 0,
+            };
+            if current_difference < difference {
+                closest = Some((nf2 as u8, no2, current_output, usb_div));
+                difference = current_difference;
+            }
+        }
+    }
+
+    // Don't silently settle for a PLL output that is far off target
+    match (closest, sys_target) {
+        (Some(closest), None) => Ok(closest),
+        (Some(closest), Some(target))
+            if difference as u64 * 100 <= target as u64 * PLL_MAX_DEVIATION_PERCENT as u64 =>
+        {
+            Ok(closest)
+        }
+        // Blame CK_USB if CK_SYS is reachable on its own
+        _ if usb_target.is_some() && pll_search(ck_in, sys_target, None).is_ok() => {
+            Err(ClockError::CkUsbUnreachable)
+        }
+        _ => Err(ClockError::PllUnreachable),
+    }
+}
+
+/// Switch CK_SYS over to `src` and wait for the switch to complete.
+///
+/// The clock has to be running already.
+fn switch_sys_clock(src: SysClkSrc) {
+    let ckcu = unsafe { &*CKCU::ptr() };
+
+    // Refer to User manual for SW values
+    let sw = match src {
+        SysClkSrc::Pll => 0b00,
+        SysClkSrc::Hse => 0b10,
+        SysClkSrc::Hsi => 0b11,
+    };
+    ckcu.ckcu_gccr.modify(|_, w| unsafe { w.sw().bits(sw) });
 
-        // Apply the calculated clock configuration
+    // The switch only happens on a clock edge of both the old and the new
+    // source, CKSWST reflects the clock that is actually in use
+    while ckcu.ckcu_ckst.read().ckswst().bits() != sw {
+        cortex_m::asm::nop();
+    }
+}
+
+impl ClockPlan {
+    /// The source of CK_SYS
+    pub fn sys_src(&self) -> SysClkSrc {
+        self.sys_src
+    }
+
+    /// The PLL configuration, if the PLL is in use
+    pub fn pll(&self) -> Option<PllConfig> {
+        self.pll
+    }
+
+    /// The AHB prescaler, HCLK = CK_SYS / AHB prescaler
+    pub fn ahb_div(&self) -> u8 {
+        self.ahb_div
+    }
+
+    /// The USB prescaler, CK_USB = CK_PLL / USB prescaler
+    pub fn usb_div(&self) -> u8 {
+        self.usb_div
+    }
+
+    /// The ADC prescaler, CK_ADC_IP = HCLK / ADC prescaler
+    pub fn adc_div(&self) -> u8 {
+        self.adc_div
+    }
+
+    /// The number of flash wait states required for HCLK
+    pub fn wait_states(&self) -> u8 {
+        self.wait_states
+    }
+
+    /// The LSE that will be started, if any
+    pub fn lse(&self) -> Option<Hertz> {
+        self.lse
+    }
+
+    /// The resulting frequency for CK_SYS
+    pub fn ck_sys(&self) -> Hertz {
+        self.ck_sys
+    }
+
+    /// The resulting frequency for HCLK
+    pub fn hclk(&self) -> Hertz {
+        self.hclk
+    }
+
+    /// The resulting frequency for CK_USB
+    pub fn ck_usb(&self) -> Hertz {
+        self.ck_usb
+    }
+
+    /// The resulting frequency for CK_ADC_IP
+    pub fn ck_adc_ip(&self) -> Hertz {
+        self.ck_adc_ip
+    }
+
+    /// The resulting frequency for STCLK
+    pub fn stclk(&self) -> Hertz {
+        self.stclk
+    }
+
+    /// Apply the planned clock configuration to the hardware
+    ///
+    /// `low_speed` holds the RTC source and LSE of an already running
+    /// configuration, the low speed oscillators are only started without it.
+    fn apply(&self, low_speed: Option<(RtcSrc, Option<Hertz>)>) -> Clocks {
         let ckcu = unsafe { &*CKCU::ptr() };
 
         // Enable backup domain, necessary for USB.
         // TODO: only do this if ck_usb is Some?
         ckcu.ckcu_lpcr.write(|w| w.bkiso().set_bit());
 
-        // First configure the PLL in case it needs to be set up
-        if pll_target_clock.is_some() {
+        // The HSI is where CK_SYS is parked while the rest of the clock tree
+        // is reconfigured, so make sure it is running
+        ckcu.ckcu_gccr.modify(|_, w| w.hsien().set_bit());
+        while !ckcu.ckcu_gcsr.read().hsirdy().bit_is_set() {
+            cortex_m::asm::nop();
+        }
+
+        // Start the HSE if anything is going to run from it
+        let pll_on_hse = self.pll.map(|pll| pll.src == PllSrc::Hse).unwrap_or(false);
+        let hse_used = self.sys_src == SysClkSrc::Hse
+            || pll_on_hse
+            || self.ckout == Some(CkoutSrc::CkHse);
+        if hse_used {
+            ckcu.ckcu_gccr.modify(|_, w| w.hseen().set_bit());
+            while !ckcu.ckcu_gcsr.read().hserdy().bit_is_set() {
+                cortex_m::asm::nop();
+            }
+        }
+
+        // Going up in frequency, the flash wait states have to be raised
+        // before HCLK increases so the chip doesn't hang.
+        // WAIT = 0b001 means zero wait states
+        let fmc = unsafe { &*FMC::ptr() };
+        let current_wait_states = fmc.fmc_cfcr.read().wait().bits().saturating_sub(1);
+        if self.wait_states > current_wait_states {
+            fmc.fmc_cfcr
+                .modify(|_, w| unsafe { w.wait().bits(self.wait_states + 1) });
+        }
+
+        // Park CK_SYS on the HSI, which is slow enough for any prescaler and
+        // wait state setting. This way the PLL is never reconfigured while
+        // it is driving CK_SYS.
+        switch_sys_clock(SysClkSrc::Hsi);
+
+        // Set the AHB prescaler
+        let ahb_div = match self.ahb_div {
+            1 => 0b00,
+            2 => 0b01,
+            4 => 0b10,
+            8 => 0b11,
+            _ => unreachable!(),
+        };
+        ckcu.ckcu_ahbcfgr
+            .modify(|_, w| unsafe { w.ahbpre().bits(ahb_div) });
+
+        // The PLL can only be reconfigured while it is disabled, it stays
+        // disabled if nothing runs from it
+        ckcu.ckcu_gccr.modify(|_, w| w.pllen().clear_bit());
+
+        if let Some(pll) = self.pll {
             // Set the source clock for the PLL
-            ckcu.ckcu_gcfgr.modify(|_, w| w.pllsrc().bit(pllsrc));
+            ckcu.ckcu_gcfgr
+                .modify(|_, w| w.pllsrc().bit(pll.src == PllSrc::Hsi));
+
+            // Map NF2 values to their respective register values
+            let nf2 = if pll.nf2 == 64 { 0 } else { pll.nf2 };
+
+            // Map NO2 values to their respective register values
+            // Refer to User manual page 88
+            let no2 = match pll.no2 {
+                1 => 0b00,
+                2 => 0b01,
+                4 => 0b10,
+                8 => 0b11,
+                _ => unreachable!(),
+            };
 
             // Set the actual configuration values
             ckcu.ckcu_pllcfgr.modify(|_, w| unsafe {
                 w.pfbd() // PFBD contains NF2
-                 .bits(nf2.unwrap())
+                 .bits(nf2)
                  .potd() // POTD contains NO2
-                 .bits(no2.unwrap())
+                 .bits(no2)
             });
 
             // Enable the PLL
@@ -358,87 +996,455 @@ impl Configuration {
             }
         }
 
-        // Set the flash wait states so the chip doesn't hang on higher frequencies
-        let fmc = unsafe { &*FMC::ptr() };
-        if hclk > 48.MHz::<1, 1>() {
-            fmc.fmc_cfcr.modify(|_, w| unsafe { w.wait().bits(0b011) });
-        } else if hclk > 24.MHz::<1, 1>() {
-            fmc.fmc_cfcr.modify(|_, w| unsafe { w.wait().bits(0b010) });
-        }
-
         // Set up the proper CK_SYS source
-        ckcu.ckcu_gccr.modify(|_, w| unsafe { w.sw().bits(sw) });
+        switch_sys_clock(self.sys_src);
 
-        // Set the AHB prescaler
-        ckcu.ckcu_ahbcfgr
-            .modify(|_, w| unsafe { w.ahbpre().bits(ahb_div) });
+        // Going down in frequency, the flash wait states can only be
+        // lowered once HCLK decreased
+        if self.wait_states < current_wait_states {
+            fmc.fmc_cfcr
+                .modify(|_, w| unsafe { w.wait().bits(self.wait_states + 1) });
+        }
+
+        // Only monitor the HSE once CK_SYS runs from its final source
+        ckcu.ckcu_gccr
+            .modify(|_, w| w.ckmen().bit(self.clock_monitor));
 
         // Set the USB prescaler
+        let usb_div = match self.usb_div {
+            1 => 0b00,
+            2 => 0b01,
+            3 => 0b10,
+            _ => unreachable!(),
+        };
         ckcu.ckcu_gcfgr
             .modify(|_, w| unsafe { w.usbpre().bits(usb_div) });
 
         // Set the ADC prescaler
+        let adc_div = match self.adc_div {
+            1 => 0b000,
+            2 => 0b001,
+            4 => 0b010,
+            6 => 0b111,
+            8 => 0b011,
+            16 => 0b100,
+            32 => 0b101,
+            64 => 0b110,
+            _ => unreachable!(),
+        };
         ckcu.ckcu_apbcfgr
             .modify(|_, w| unsafe { w.adcdiv().bits(adc_div) });
 
         // After all clocks are set up, configure CKOUT if required
         if let Some(ckout) = self.ckout {
-            let ckout = match ckout {
-                CkoutSrc::CkRef => 0b000,
-                CkoutSrc::Hclk => 0b001,
-                CkoutSrc::CkSys => 0b010,
-                CkoutSrc::CkHse => 0b011,
-                CkoutSrc::CkHsi => 0b100,
-                CkoutSrc::CkLse => 0b101,
-                CkoutSrc::CkLsi => 0b110,
-            };
-
             ckcu.ckcu_gcfgr
-                .modify(|_, w| unsafe { w.ckoutsrc().bits(ckout) });
+                .modify(|_, w| unsafe { w.ckoutsrc().bits(ckout.bits()) });
         }
 
-        // Reset AFIO here because the GPIO implementation is block wise ->
-        // Resetting AFIO during GPIO initialization could lead to already being
-        // used pins / their AF being reset.
-        (unsafe { &*RSTCU::ptr() })
-            .rstcu_apbprstr0
-            .modify(|_, w| w.afiorst().set_bit());
+        // CK_REF is derived from CK_PLL by its own prescaler, which is
+        // left at whatever it is set to
+        #[cfg(any(
+            feature = "ht32f1653",
+            feature = "ht32f1654",
+            feature = "ht32f1655",
+            feature = "ht32f1656",
+        ))]
+        let ck_ref = {
+            let ck_ref_div = (ckcu.ckcu_gcfgr.read().ckrefpre().bits() as u32 + 1) * 2;
+            self.pll.map(|pll| pll.ck_pll / ck_ref_div)
+        };
+
+        // Bring up the low speed oscillator for the RTC, unless it is
+        // already running
+        let (rtc_src, lse) = low_speed.unwrap_or_else(|| self.start_low_speed_oscillator());
 
         Clocks {
             ckout: self.ckout,
+            ck_usb: self.ck_usb,
+            ck_adc_ip: self.ck_adc_ip,
+            ck_sys: self.ck_sys,
+            stclk: self.stclk,
+            hclk: self.hclk,
+            sys_src: self.sys_src,
+            pll: self.pll,
+            wait_states: self.wait_states,
+            ahb_div: self.ahb_div,
+            adc_div: self.adc_div,
+            rtc_src,
+            lse,
+            #[cfg(any(
+                feature = "ht32f1653",
+                feature = "ht32f1654",
+                feature = "ht32f1655",
+                feature = "ht32f1656",
+            ))]
+            ck_ref,
+            hse: if hse_used { self.hse } else { None },
+        }
+    }
+
+    /// Start the LSE if one is given, falling back on the LSI should it not
+    /// become ready in time. The chosen oscillator is selected as CK_RTC.
+    ///
+    /// Without an LSE the backup domain is left alone, so an RTC that keeps
+    /// running from battery power across resets isn't switched over, the
+    /// current CK_RTC source is reported instead.
+    ///
+    /// The backup domain has to be accessible and HCLK has to be set up
+    /// already, as it is used to time the LSE startup.
+    fn start_low_speed_oscillator(&self) -> (RtcSrc, Option<Hertz>) {
+        let ckcu = unsafe { &*CKCU::ptr() };
+        let rtc = unsafe { &*RTC::ptr() };
+
+        // The RTC registers, which also control the LSE and LSI, can only be
+        // accessed with the RTC APB clock enabled
+        ckcu.ckcu_apbccr1.modify(|_, w| w.rtcen().set_bit());
+
+        if let Some(lse) = self.lse {
+            rtc.rtc_cr.modify(|_, w| {
+                w.lsesm()
+                 .bit(self.lse_drive == LseDrive::Fast)
+                 .lseen()
+                 .set_bit()
+            });
+
+            // Poll the ready flag once every millisecond until the timeout
+            let cycles_per_ms = self.hclk.raw() / 1_000;
+            let mut ready = ckcu.ckcu_gcsr.read().lserdy().bit_is_set();
+            for _ in 0..self.lse_timeout.ticks() {
+                if ready {
+                    break;
+                }
+                cortex_m::asm::delay(cycles_per_ms);
+                ready = ckcu.ckcu_gcsr.read().lserdy().bit_is_set();
+            }
+
+            if ready {
+                rtc.rtc_cr.modify(|_, w| w.rtcsrc().set_bit());
+                return (RtcSrc::Lse, Some(lse));
+            }
+
+            // The crystal did not start, stop driving it
+            rtc.rtc_cr.modify(|_, w| w.lseen().clear_bit());
+        } else if rtc.rtc_cr.read().rtcsrc().bit_is_set() {
+            return (RtcSrc::Lse, None);
+        } else {
+            return (RtcSrc::Lsi, None);
+        }
+
+        rtc.rtc_cr.modify(|_, w| w.lsien().set_bit());
+        while !ckcu.ckcu_gcsr.read().lsirdy().bit_is_set() {
+            cortex_m::asm::nop();
+        }
+        rtc.rtc_cr.modify(|_, w| w.rtcsrc().clear_bit());
+
+        (RtcSrc::Lsi, None)
+    }
+}
+
+/// HSE clock monitor
+///
+/// When enabled through [`Configuration::enable_clock_monitor`], the
+/// hardware switches CK_SYS back to the HSI as soon as the HSE fails and
+/// sets the clock stuck flag. The HSE and any PLL running from it are
+/// stopped, so all frequencies derived from them are no longer valid.
+///
+/// To handle a failure, move the `ClockMonitor` and the `Clocks` somewhere
+/// the `CKRDY` interrupt handler can reach them, [`listen`](Self::listen)
+/// and unmask `CKRDY` in the NVIC. In the handler, check
+/// [`has_failed`](Self::has_failed) and pass the `Clocks` through
+/// [`recover`](Self::recover), which also clears the flag. Drivers set up
+/// with the old `Clocks` have to be set up again afterwards, see
+/// `examples/clock_monitor.rs`.
+pub struct ClockMonitor {
+    _private: (),
+}
+
+impl ClockMonitor {
+    /// Enable the clock stuck interrupt, which is handled by the `CKRDY`
+    /// interrupt vector
+    pub fn listen(&mut self) {
+        let ckcu = unsafe { &*CKCU::ptr() };
+        let enabled = Self::enabled_interrupts();
+        ckcu.ckcu_gcir
+            .write(|w| unsafe { w.bits(enabled) }.cksie().set_bit());
+    }
+
+    /// Disable the clock stuck interrupt
+    pub fn unlisten(&mut self) {
+        let ckcu = unsafe { &*CKCU::ptr() };
+        let enabled = Self::enabled_interrupts();
+        ckcu.ckcu_gcir
+            .write(|w| unsafe { w.bits(enabled) }.cksie().clear_bit());
+    }
+
+    /// The interrupt enable bits in CKCU_GCIR, without the flags.
+    ///
+    /// The lower half holds the write 1 to clear flags, writing back what
+    /// was read would clear any flag that happens to be set.
+    fn enabled_interrupts() -> u32 {
+        let ckcu = unsafe { &*CKCU::ptr() };
+        ckcu.ckcu_gcir.read().bits() & 0xffff_0000
+    }
+
+    /// Check whether an HSE failure was detected
+    pub fn has_failed(&self) -> bool {
+        let ckcu = unsafe { &*CKCU::ptr() };
+        ckcu.ckcu_gcir.read().cksf().bit_is_set()
+    }
+
+    /// Clear the clock stuck flag, this has to be done in the interrupt
+    /// handler or it will fire again right away
+    pub fn clear(&mut self) {
+        let ckcu = unsafe { &*CKCU::ptr() };
+        // The flag is cleared by writing 1, leave the other flags alone
+        let enabled = Self::enabled_interrupts();
+        ckcu.ckcu_gcir
+            .write(|w| unsafe { w.bits(enabled) }.cksf().set_bit());
+    }
+
+    /// Clear the clock stuck flag and re-derive the frozen clock frequencies
+    /// after the hardware switched CK_SYS back to the HSI.
+    ///
+    /// The prescalers are left untouched by the fallback, so everything
+    /// below CK_SYS scales down with it. Should CK_SYS still run from its
+    /// original source, `clocks` is returned unchanged.
+    pub fn recover(&mut self, clocks: Clocks) -> Clocks {
+        self.clear();
+
+        let ckcu = unsafe { &*CKCU::ptr() };
+        // Refer to User manual for SW values
+        if ckcu.ckcu_gccr.read().sw().bits() != 0b11 || clocks.sys_src == SysClkSrc::Hsi {
+            return clocks;
+        }
+
+        // A PLL running from the HSE is stopped together with it
+        let pll = clocks.pll.filter(|pll| pll.src == PllSrc::Hsi);
+        let ck_usb = match pll {
+            Some(_) => clocks.ck_usb,
+            None => 0.Hz(),
+        };
+
+        let ck_sys = HSI.Hz::<1, 1>();
+        let hclk = (ck_sys.raw() / clocks.ahb_div as u32).Hz::<1, 1>();
+
+        Clocks {
             ck_usb,
-            ck_adc_ip,
+            ck_adc_ip: (hclk.raw() / clocks.adc_div as u32).Hz(),
             ck_sys,
-            stclk,
+            stclk: (hclk.raw() / 8).Hz(),
             hclk,
+            sys_src: SysClkSrc::Hsi,
+            pll,
+            #[cfg(any(
+                feature = "ht32f1653",
+                feature = "ht32f1654",
+                feature = "ht32f1655",
+                feature = "ht32f1656",
+            ))]
+            ck_ref: pll.and(clocks.ck_ref),
+            hse: None,
+            ..clocks
+        }
+    }
+}
+
+/// Pins that can output CKOUT, see the alternate function table of
+/// the device datasheet
+pub trait PinCkout {
+    #[doc(hidden)]
+    fn route(&self, afio: &mut Afio, enable: bool);
+}
+
+/// Clock output on the CKOUT pin
+///
+/// CKOUT can not be gated in the CKCU itself, so disabling it hands the
+/// pin back to its default function (AF0).
+pub struct Ckout<PIN> {
+    pin: PIN,
+    src: CkoutSrc,
+    enabled: bool,
+}
+
+impl<PIN: PinCkout> Ckout<PIN> {
+    /// Output `src` on the given pin
+    pub fn new(pin: PIN, src: CkoutSrc) -> Self {
+        let mut ckout = Ckout {
+            pin,
+            src,
+            enabled: true,
+        };
+        ckout.set_source(src);
+        ckout
+    }
+
+    /// Switch the clock that is outputted
+    ///
+    /// CK_HSE and CK_LSE are not started by this, only output if they are
+    /// running already.
+    pub fn set_source(&mut self, src: CkoutSrc) {
+        let ckcu = unsafe { &*CKCU::ptr() };
+        ckcu.ckcu_gcfgr
+            .modify(|_, w| unsafe { w.ckoutsrc().bits(src.bits()) });
+        self.src = src;
+    }
+
+    /// Returns the clock that is outputted
+    pub fn source(&self) -> CkoutSrc {
+        self.src
+    }
+
+    /// Route CKOUT to the pin again
+    pub fn enable(&mut self, afio: &mut Afio) {
+        self.pin.route(afio, true);
+        self.enabled = true;
+    }
+
+    /// Stop outputting the clock on the pin
+    pub fn disable(&mut self, afio: &mut Afio) {
+        self.pin.route(afio, false);
+        self.enabled = false;
+    }
+
+    /// Returns whether the clock is outputted on the pin
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Returns the frequency on the pin, `None` if it is disabled or the
+    /// frequency of the source is not known, see
+    /// [`Clocks::ckout_frequency`]
+    pub fn frequency(&self, clocks: &Clocks) -> Option<Hertz> {
+        if self.enabled {
+            clocks.ckout_frequency(self.src)
+        } else {
+            None
         }
     }
+
+    /// Release the pin, routed to CKOUT as its type says
+    pub fn free(mut self, afio: &mut Afio) -> PIN {
+        self.enable(afio);
+        self.pin
+    }
+}
+
+#[cfg(any(
+    feature = "ht32f1653",
+    feature = "ht32f1654",
+    feature = "ht32f1655",
+    feature = "ht32f1656",
+))]
+macro_rules! ckout_pins {
+    ($($PIN:ty: $af:expr,)+) => {
+        $(
+            impl PinCkout for $PIN {
+                fn route(&self, afio: &mut Afio, enable: bool) {
+                    self.into_alternate(afio, if enable { $af } else { 0 });
+                }
+            }
+        )+
+    }
+}
+
+#[cfg(any(
+    feature = "ht32f1653",
+    feature = "ht32f1654",
+    feature = "ht32f1655",
+    feature = "ht32f1656",
+))]
+use crate::gpio::{afio::AfioCfg, gpioa::PA9, Output, PushPull, AF15};
+
+#[cfg(any(
+    feature = "ht32f1653",
+    feature = "ht32f1654",
+    feature = "ht32f1655",
+    feature = "ht32f1656",
+))]
+ckout_pins!(
+    PA9<Output<PushPull>, AF15>: 15,
+);
+
+// TODO: ckout_pins! for the HT32F125x and HT32F175x, the CKOUT pin and AF
+// still have to be taken from their datasheets
+
+/// Proof of ownership over the peripheral clock enable and reset
+/// registers, required by [`Enable`] and [`Reset`]
+pub struct PeripheralControl {
+    _private: (),
+}
+
+/// Gate the bus clock of a peripheral
+pub trait Enable {
+    /// Enable the bus clock of the peripheral
+    fn enable(pc: &mut PeripheralControl);
+    /// Disable the bus clock of the peripheral, its registers can not be
+    /// accessed until it is enabled again
+    fn disable(pc: &mut PeripheralControl);
+    /// Returns whether the bus clock of the peripheral is enabled
+    fn is_enabled(pc: &PeripheralControl) -> bool;
+}
+
+/// Reset a peripheral to its power on state
+pub trait Reset {
+    /// Reset the peripheral
+    fn reset(pc: &mut PeripheralControl);
 }
 
 /// Peripheral Clock Enable and Reset
 pub(crate) trait Pcer {
     fn enable(&self);
-    fn disable(&self);
     fn reset(&self);
 }
 
-macro_rules! pcer {
+// The drivers own their peripheral, which is proof enough to touch its
+// clock gate and reset bit
+impl<T: Enable + Reset> Pcer for T {
+    fn enable(&self) {
+        <T as Enable>::enable(&mut PeripheralControl { _private: () });
+    }
+
+    fn reset(&self) {
+        <T as Reset>::reset(&mut PeripheralControl { _private: () });
+    }
+}
+
+macro_rules! clock_gate {
     (
-        $(($PERI:ident, $bccr:ident, $bccb:ident, $brstr:ident, $brstb:ident),)+
+        $(($PERI:ident, $bccr:ident, $bccb:ident),)+
     ) => {
         $(
-            impl Pcer for crate::pac::$PERI {
-                fn enable(&self) {
+            impl Enable for crate::pac::$PERI {
+                fn enable(_pc: &mut PeripheralControl) {
                     let ckcu = unsafe { &*CKCU::ptr() };
                     ckcu.$bccr.modify(|_, w| w.$bccb().set_bit());
                 }
 
-                fn disable(&self) {
+                fn disable(_pc: &mut PeripheralControl) {
                     let ckcu = unsafe { &*CKCU::ptr() };
                     ckcu.$bccr.modify(|_, w| w.$bccb().clear_bit());
                 }
 
-                fn reset(&self) {
+                fn is_enabled(_pc: &PeripheralControl) -> bool {
+                    let ckcu = unsafe { &*CKCU::ptr() };
+                    ckcu.$bccr.read().$bccb().bit_is_set()
+                }
+            }
+        )+
+    }
+}
+
+macro_rules! pcer {
+    (
+        $(($PERI:ident, $bccr:ident, $bccb:ident, $brstr:ident, $brstb:ident),)+
+    ) => {
+        clock_gate!(
+            $(($PERI, $bccr, $bccb),)+
+        );
+
+        $(
+            impl Reset for crate::pac::$PERI {
+                fn reset(_pc: &mut PeripheralControl) {
                     let rstcu = unsafe { &*RSTCU::ptr() };
                     rstcu.$brstr.modify(|_, w| w.$brstb().set_bit());
                 }
@@ -461,6 +1467,111 @@ pcer!(
     (BFTM0, ckcu_apbccr1, bftm0en, rstcu_apbprstr1, bftm0rst),
     (BFTM1, ckcu_apbccr1, bftm1en, rstcu_apbprstr1, bftm1rst),
     (ADC,   ckcu_apbccr1, adcen,   rstcu_apbprstr1, adcrst),
+    (WDT,   ckcu_apbccr1, wdten,   rstcu_apbprstr1, wdtrst),
+);
+
+// The RTC lives in the backup domain, it is only reset together with it
+clock_gate!(
+    (RTC, ckcu_apbccr1, rtcen),
+);
+
+// MCTM
+#[cfg(any(feature = "ht32f1755", feature = "ht32f1765"))]
+pcer!(
+    (MCTM, ckcu_apbccr1, mctmen, rstcu_apbprstr1, mctmrst),
+);
+
+#[cfg(any(
+    feature = "ht32f1653",
+    feature = "ht32f1654",
+    feature = "ht32f1655",
+    feature = "ht32f1656",
+))]
+pcer!(
+    (MCTM0, ckcu_apbccr1, mctm0en, rstcu_apbprstr1, mctm0rst),
+    (MCTM1, ckcu_apbccr1, mctm1en, rstcu_apbprstr1, mctm1rst),
+);
+
+// CMP / OPA
+#[cfg(any(feature = "ht32f1653", feature = "ht32f1654"))]
+pcer!(
+    (CMP, ckcu_apbccr1, cmpen, rstcu_apbprstr1, cmprst),
+);
+
+// Both OPA/CMP units share the OPACMP register block, but each has its own
+// clock gate and reset bit
+#[cfg(any(
+    feature = "ht32f1251",
+    feature = "ht32f1252",
+    feature = "ht32f1253",
+    feature = "ht32f1655",
+    feature = "ht32f1656",
+    feature = "ht32f1755",
+    feature = "ht32f1765",
+))]
+mod opacmp {
+    use super::{Enable, PeripheralControl, Reset, CKCU, RSTCU};
+    use crate::pac::OPACMP;
+
+    impl Enable for OPACMP {
+        fn enable(_pc: &mut PeripheralControl) {
+            let ckcu = unsafe { &*CKCU::ptr() };
+            ckcu.ckcu_apbccr1
+                .modify(|_, w| w.opa0en().set_bit().opa1en().set_bit());
+        }
+
+        fn disable(_pc: &mut PeripheralControl) {
+            let ckcu = unsafe { &*CKCU::ptr() };
+            ckcu.ckcu_apbccr1
+                .modify(|_, w| w.opa0en().clear_bit().opa1en().clear_bit());
+        }
+
+        fn is_enabled(_pc: &PeripheralControl) -> bool {
+            let ckcu = unsafe { &*CKCU::ptr() };
+            let r = ckcu.ckcu_apbccr1.read();
+            r.opa0en().bit_is_set() && r.opa1en().bit_is_set()
+        }
+    }
+
+    impl Reset for OPACMP {
+        fn reset(_pc: &mut PeripheralControl) {
+            let rstcu = unsafe { &*RSTCU::ptr() };
+            rstcu
+                .rstcu_apbprstr1
+                .modify(|_, w| w.opa0rst().set_bit().opa1rst().set_bit());
+        }
+    }
+}
+
+// PDMA, EBI
+#[cfg(not(any(
+    feature = "ht32f1251",
+    feature = "ht32f1252",
+    feature = "ht32f1253",
+)))]
+pcer!(
+    (PDMA, ckcu_ahbccr, pdmaen, rstcu_ahbprstr, dmarst),
+);
+
+#[cfg(any(
+    feature = "ht32f1653",
+    feature = "ht32f1654",
+    feature = "ht32f1655",
+    feature = "ht32f1656",
+))]
+pcer!(
+    (EBI, ckcu_ahbccr, ebien, rstcu_ahbprstr, ebirst),
+);
+
+// I2S
+#[cfg(any(
+    feature = "ht32f1653",
+    feature = "ht32f1654",
+    feature = "ht32f1655",
+    feature = "ht32f1656",
+))]
+pcer!(
+    (I2S, ckcu_apbccr0, i2sen, rstcu_apbprstr0, i2srst),
 );
 
 // UART
@@ -537,3 +1648,160 @@ pcer!(
 pcer!(
     (CRC, ckcu_ahbccr, crcen, rstcu_ahbprstr, crcrst),
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Configuration {
+        Configuration {
+            ckout: None,
+            hse: None,
+            lse: None,
+            lse_drive: LseDrive::Normal,
+            lse_timeout: MilliSeconds::from_ticks(LSE_TIMEOUT_MS),
+            ck_usb: None,
+            ck_adc_ip: None,
+            hclk: None,
+            ck_sys: None,
+            clock_monitor: false,
+        }
+    }
+
+    #[test]
+    fn hsi_by_default() {
+        let plan = config().plan().unwrap();
+        assert_eq!(plan.sys_src(), SysClkSrc::Hsi);
+        assert_eq!(plan.pll(), None);
+        assert_eq!(plan.ck_sys(), 8.MHz::<1, 1>());
+        assert_eq!(plan.hclk(), 8.MHz::<1, 1>());
+        assert_eq!(plan.stclk(), 1.MHz::<1, 1>());
+        assert_eq!(plan.wait_states(), 0);
+    }
+
+    #[test]
+    fn hse_preferred_over_hsi() {
+        let plan = config().use_hse(12.MHz()).plan().unwrap();
+        assert_eq!(plan.sys_src(), SysClkSrc::Hse);
+        assert_eq!(plan.pll(), None);
+        assert_eq!(plan.ck_sys(), 12.MHz::<1, 1>());
+
+        let plan = config().use_hse(12.MHz()).ck_sys(12.MHz()).plan().unwrap();
+        assert_eq!(plan.sys_src(), SysClkSrc::Hse);
+
+        let plan = config().use_hse(12.MHz()).enable_clock_monitor().plan();
+        assert!(plan.is_ok());
+    }
+
+    #[test]
+    fn pll_from_hse() {
+        let plan = config().use_hse(8.MHz()).ck_sys(72.MHz()).plan().unwrap();
+        let pll = plan.pll().unwrap();
+        assert_eq!(plan.sys_src(), SysClkSrc::Pll);
+        assert_eq!(pll.src(), PllSrc::Hse);
+        assert_eq!((pll.nf2(), pll.no2()), (9, 1));
+        assert_eq!(plan.ck_sys(), 72.MHz::<1, 1>());
+        assert_eq!(plan.hclk(), 72.MHz::<1, 1>());
+        assert_eq!(plan.wait_states(), 2);
+    }
+
+    #[test]
+    fn pll_from_hsi() {
+        let plan = config().ck_sys(48.MHz()).plan().unwrap();
+        let pll = plan.pll().unwrap();
+        assert_eq!(pll.src(), PllSrc::Hsi);
+        assert_eq!((pll.nf2(), pll.no2()), (12, 2));
+        assert_eq!(plan.ck_sys(), 48.MHz::<1, 1>());
+        assert_eq!(plan.wait_states(), 1);
+    }
+
+    #[test]
+    fn pll_within_tolerance() {
+        // 72.5 Mhz is out of reach, 72 Mhz is within 1%
+        let plan = config().ck_sys(72_500.kHz()).plan().unwrap();
+        assert_eq!(plan.ck_sys(), 72.MHz::<1, 1>());
+    }
+
+    #[test]
+    fn prescalers() {
+        let plan = config()
+            .use_hse(8.MHz())
+            .ck_sys(144.MHz())
+            .hclk(72.MHz())
+            .ck_adc_ip(12.MHz())
+            .plan()
+            .unwrap();
+        assert_eq!(plan.ahb_div(), 2);
+        assert_eq!(plan.hclk(), 72.MHz::<1, 1>());
+        assert_eq!(plan.adc_div(), 6);
+        assert_eq!(plan.ck_adc_ip(), 12.MHz::<1, 1>());
+    }
+
+    #[test]
+    fn usb_divider_search() {
+        let plan = config()
+            .use_hse(8.MHz())
+            .ck_sys(144.MHz())
+            .hclk(72.MHz())
+            .ck_usb(48.MHz())
+            .plan()
+            .unwrap();
+        assert_eq!(plan.usb_div(), 3);
+        assert_eq!(plan.ck_usb(), 48.MHz::<1, 1>());
+
+        // CK_SYS stays on the HSE, the PLL only runs for CK_USB
+        let plan = config().use_hse(8.MHz()).ck_usb(48.MHz()).plan().unwrap();
+        assert_eq!(plan.sys_src(), SysClkSrc::Hse);
+        assert_eq!(plan.pll().unwrap().ck_pll(), 96.MHz::<1, 1>());
+        assert_eq!(plan.usb_div(), 2);
+        assert_eq!(plan.ck_usb(), 48.MHz::<1, 1>());
+    }
+
+    #[test]
+    fn pll_search_results() {
+        assert_eq!(
+            pll_search(8_000_000, Some(72_000_000), None),
+            Ok((9, 1, 72_000_000, None))
+        );
+        assert_eq!(
+            pll_search(8_000_000, None, Some(48_000_000)),
+            Ok((12, 1, 96_000_000, Some(2)))
+        );
+        assert_eq!(
+            pll_search(8_000_000, Some(8_500_000), None),
+            Err(ClockError::PllUnreachable)
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let err = |config: Configuration| config.plan().unwrap_err();
+
+        assert_eq!(err(config().ck_sys(145.MHz())), ClockError::CkSysTooHigh);
+        assert_eq!(
+            err(config().enable_clock_monitor()),
+            ClockError::ClockMonitorWithoutHse
+        );
+        assert_eq!(err(config().ck_usb(49.MHz())), ClockError::CkUsbTooHigh);
+        assert_eq!(err(config().ck_sys(96.MHz())), ClockError::HclkTooHigh);
+        assert_eq!(
+            err(config().ck_sys(8_500.kHz())),
+            ClockError::PllUnreachable
+        );
+        assert_eq!(err(config().hclk(16.MHz())), ClockError::HclkUnreachable);
+        assert_eq!(err(config().hclk(0.Hz())), ClockError::HclkUnreachable);
+        assert_eq!(
+            err(config().use_hse(8.MHz()).ck_sys(72.MHz()).ck_usb(48.MHz())),
+            ClockError::CkUsbUnreachable
+        );
+        assert_eq!(err(config().ck_usb(0.Hz())), ClockError::CkUsbUnreachable);
+        assert_eq!(
+            err(config().ck_adc_ip(16.MHz())),
+            ClockError::CkAdcUnreachable
+        );
+        assert_eq!(
+            err(config().ck_adc_ip(0.Hz())),
+            ClockError::CkAdcUnreachable
+        );
+    }
+}
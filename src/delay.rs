@@ -0,0 +1,77 @@
+//! Delays
+use crate::ckcu::Clocks;
+use crate::time::Hertz;
+
+use cortex_m::peripheral::syst::SystClkSource;
+use cortex_m::peripheral::SYST;
+use embedded_hal::delay::DelayNs;
+
+/// The SysTick reload value is only 24 bit wide
+const MAX_RELOAD: u32 = 0x00FF_FFFF;
+
+/// Blocking delay based on the SysTick, which runs from STCLK
+pub struct SysDelay {
+    syst: SYST,
+    stclk: Hertz,
+}
+
+impl SysDelay {
+    /// Configure the SysTick to run from STCLK, aka HCLK / 8
+    pub fn new(mut syst: SYST, clocks: &Clocks) -> Self {
+        syst.set_clock_source(SystClkSource::External);
+
+        Self {
+            syst,
+            stclk: clocks.stclk(),
+        }
+    }
+
+    /// Release the SysTick
+    pub fn free(self) -> SYST {
+        self.syst
+    }
+
+    /// Convert `amount` units of 1 / `per_second` seconds into STCLK
+    /// cycles, rounding up so the delay is never shorter than requested
+    fn ticks(&self, amount: u32, per_second: u64) -> u64 {
+        // Round up by hand, `u64::div_ceil` is newer than the MSRV
+        let scaled = amount as u64 * self.stclk.raw() as u64;
+        let ticks = scaled / per_second;
+        if ticks * per_second < scaled {
+            ticks + 1
+        } else {
+            ticks
+        }
+    }
+
+    /// Wait for at least `ticks` STCLK cycles, splitting longer delays
+    /// into multiple SysTick wraps
+    fn delay_ticks(&mut self, mut ticks: u64) {
+        while ticks != 0 {
+            let current = ticks.min(MAX_RELOAD as u64) as u32;
+            ticks -= current as u64;
+
+            self.syst.set_reload(current);
+            self.syst.clear_current();
+            self.syst.enable_counter();
+
+            while !self.syst.has_wrapped() {}
+
+            self.syst.disable_counter();
+        }
+    }
+}
+
+impl DelayNs for SysDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        self.delay_ticks(self.ticks(ns, 1_000_000_000));
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        self.delay_ticks(self.ticks(us, 1_000_000));
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.delay_ticks(self.ticks(ms, 1_000));
+    }
+}
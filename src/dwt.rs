@@ -0,0 +1,219 @@
+//! Data Watchpoint and Trace unit, used for its cycle counter
+use crate::ckcu::Clocks;
+use crate::time::{Hertz, MicroSeconds, MilliSeconds, NanoSeconds};
+
+use core::cell::Cell;
+use core::marker::PhantomData;
+
+use cortex_m::interrupt::Mutex;
+use cortex_m::peripheral::{DCB, DWT};
+use embedded_hal::delay::DelayNs;
+
+/// The cycle counter extended to 64 bit, as of the last time it was read
+static CYCLES: Mutex<Cell<u64>> = Mutex::new(Cell::new(0));
+
+/// Read the cycle counter, extended to 64 bit
+///
+/// The counter has no overflow interrupt, a wrap is noticed by comparing
+/// with the previous reading. The result never decreases, but a wrap is
+/// missed if the counter is not read for a whole wrap.
+fn cycle_count() -> u64 {
+    cortex_m::interrupt::free(|cs| {
+        let last = CYCLES.borrow(cs);
+        let mut cycles = (last.get() & !(u32::MAX as u64)) | DWT::cycle_count() as u64;
+        if cycles < last.get() {
+            cycles += 1 << 32;
+        }
+        last.set(cycles);
+        cycles
+    })
+}
+
+/// Extension trait that constrains the `DWT` peripheral
+pub trait DwtExt {
+    /// Enable the cycle counter, which runs from HCLK
+    fn constrain(self, dcb: DCB, clocks: &Clocks) -> Dwt;
+}
+
+impl DwtExt for DWT {
+    fn constrain(mut self, mut dcb: DCB, clocks: &Clocks) -> Dwt {
+        dcb.enable_trace();
+        DWT::unlock();
+        self.enable_cycle_counter();
+
+        Dwt {
+            dwt: self,
+            dcb,
+            hclk: clocks.hclk(),
+        }
+    }
+}
+
+/// DWT with the cycle counter running
+pub struct Dwt {
+    dwt: DWT,
+    dcb: DCB,
+    hclk: Hertz,
+}
+
+impl Dwt {
+    /// Stop the cycle counter and release the peripherals
+    pub fn release(mut self) -> (DWT, DCB) {
+        self.dwt.disable_cycle_counter();
+        (self.dwt, self.dcb)
+    }
+
+    /// Returns the current value of the cycle counter
+    pub fn now(&self) -> Instant {
+        Instant {
+            cycles: cycle_count(),
+            hclk: self.hclk,
+        }
+    }
+
+    /// Measure how long `f` takes to run, see [`Instant`] for durations
+    /// longer than one wrap of the cycle counter
+    pub fn measure<F: FnOnce()>(&self, f: F) -> ClockDuration {
+        let start = self.now();
+        f();
+        start.elapsed()
+    }
+
+    /// Returns a busy waiting delay based on the cycle counter
+    ///
+    /// The delay borrows the `Dwt`, so the cycle counter can not be
+    /// stopped with [`release`](Self::release) while it is in use.
+    pub fn delay(&self) -> DwtDelay<'_> {
+        DwtDelay {
+            hclk: self.hclk,
+            _dwt: PhantomData,
+        }
+    }
+}
+
+/// A point in time, as seen by the cycle counter
+///
+/// The 32 bit cycle counter is extended to 64 bit in software, so instants
+/// are monotonic. As the counter has no overflow interrupt, a wrap is only
+/// counted if the counter is read at least once per wrap, every ~59 s at
+/// 72 MHz, e.g. with [`Dwt::now`] or [`Instant::elapsed`]. Durations
+/// spanning an unobserved wrap come out one wrap too short.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Instant {
+    cycles: u64,
+    hclk: Hertz,
+}
+
+impl Instant {
+    /// Returns the number of HCLK cycles counted up to this instant
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Returns the time passed since this instant
+    pub fn elapsed(&self) -> ClockDuration {
+        ClockDuration {
+            cycles: cycle_count() - self.cycles,
+            hclk: self.hclk,
+        }
+    }
+
+    /// Returns the time passed between `earlier` and this instant, zero
+    /// if `earlier` is later
+    pub fn duration_since(&self, earlier: Instant) -> ClockDuration {
+        ClockDuration {
+            cycles: self.cycles.saturating_sub(earlier.cycles),
+            hclk: self.hclk,
+        }
+    }
+}
+
+/// A number of HCLK cycles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockDuration {
+    cycles: u64,
+    hclk: Hertz,
+}
+
+impl ClockDuration {
+    /// Returns the number of HCLK cycles
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Convert `1 / per_second` seconds, rounded down and saturating at
+    /// `u32::MAX`
+    fn convert(&self, per_second: u64) -> u32 {
+        let ticks = self.cycles as u128 * per_second as u128 / self.hclk.raw() as u128;
+        ticks.min(u32::MAX as u128) as u32
+    }
+
+    /// Returns the duration in nanoseconds, saturating at `u32::MAX`
+    pub fn as_nanos(&self) -> NanoSeconds {
+        NanoSeconds::from_ticks(self.convert(1_000_000_000))
+    }
+
+    /// Returns the duration in microseconds, saturating at `u32::MAX`
+    pub fn as_micros(&self) -> MicroSeconds {
+        MicroSeconds::from_ticks(self.convert(1_000_000))
+    }
+
+    /// Returns the duration in milliseconds, saturating at `u32::MAX`
+    pub fn as_millis(&self) -> MilliSeconds {
+        MilliSeconds::from_ticks(self.convert(1_000))
+    }
+}
+
+/// Busy waiting delay based on the cycle counter
+///
+/// Unlike [`SysDelay`](crate::delay::SysDelay) this does not occupy the
+/// SysTick, it only requires the cycle counter to keep running.
+#[derive(Debug, Clone, Copy)]
+pub struct DwtDelay<'a> {
+    hclk: Hertz,
+    _dwt: PhantomData<&'a Dwt>,
+}
+
+impl DwtDelay<'_> {
+    /// Wait for at least `cycles` HCLK cycles
+    fn delay_cycles(&mut self, mut cycles: u64) {
+        // Only wait half a wrap at once, so the wait can not be missed
+        // when interrupted
+        const MAX_CYCLES: u64 = (u32::MAX / 2) as u64;
+
+        while cycles != 0 {
+            let current = cycles.min(MAX_CYCLES) as u32;
+            cycles -= current as u64;
+
+            let start = DWT::cycle_count();
+            while DWT::cycle_count().wrapping_sub(start) < current {}
+        }
+    }
+
+    /// Convert `amount` units of 1 / `per_second` seconds into HCLK
+    /// cycles, rounding up so the delay is never shorter than requested
+    fn cycles(&self, amount: u32, per_second: u64) -> u64 {
+        // Round up by hand, `u64::div_ceil` is newer than the MSRV
+        let scaled = amount as u64 * self.hclk.raw() as u64;
+        let cycles = scaled / per_second;
+        if cycles * per_second < scaled {
+            cycles + 1
+        } else {
+            cycles
+        }
+    }
+}
+
+impl DelayNs for DwtDelay<'_> {
+    fn delay_ns(&mut self, ns: u32) {
+        self.delay_cycles(self.cycles(ns, 1_000_000_000));
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        self.delay_cycles(self.cycles(us, 1_000_000));
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.delay_cycles(self.cycles(ms, 1_000));
+    }
+}
@@ -20,9 +20,108 @@ impl Afio {
 }
 
 pub(crate) trait AfioCfg {
+    #[allow(clippy::wrong_self_convention)]
     fn into_alternate(&self, afio: &mut Afio, af: u8);
 }
 
+/// Number of alternate functions per pin
+#[cfg(feature = "afio4")]
+pub(crate) const AF_COUNT: u8 = 4;
+/// Number of alternate functions per pin
+#[cfg(not(feature = "afio4"))]
+pub(crate) const AF_COUNT: u8 = 16;
+
+/// An alternate function that exists on the device, for selecting the AF
+/// of an erased pin at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AfNumber(u8);
+
+impl AfNumber {
+    /// AF0, the default function of a pin
+    pub const DEFAULT: AfNumber = AfNumber(0);
+
+    /// The function that connects a pin to its ADC input, see
+    /// [`AnalogAf`](super::AnalogAf)
+    #[cfg(not(feature = "afio4"))]
+    pub(crate) const ADC: AfNumber = AfNumber(2);
+    /// The function that connects a pin to its ADC input, see
+    /// [`AnalogAf`](super::AnalogAf)
+    #[cfg(feature = "afio4")]
+    pub(crate) const ADC: AfNumber = AfNumber(0);
+
+    /// Returns `None` if the device has no alternate function `af`
+    pub const fn new(af: u8) -> Option<Self> {
+        if af < AF_COUNT {
+            Some(AfNumber(af))
+        } else {
+            None
+        }
+    }
+
+    /// The number of the alternate function
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+}
+
+/// Select the alternate function of a pin that is only known at runtime
+pub(crate) fn set_af(_afio: &mut Afio, port: u8, pin: u8, af: AfNumber) {
+    let af = af.get();
+    // NOTE (Safety): we already have an exclusive reference to AFIO
+    let afio = unsafe { &*AFIO::ptr() };
+
+    // afio4 devices have a single register per port with 2 bits per pin,
+    // the others a low and a high register with 4 bits per pin
+    #[cfg(feature = "afio4")]
+    let (shift, high) = (pin * 2, false);
+    #[cfg(not(feature = "afio4"))]
+    let (shift, high) = ((pin % 8) * 4, pin >= 8);
+    let mask = (AF_COUNT as u32 - 1) << shift;
+    let update = |bits: u32| (bits & !mask) | ((af as u32) << shift);
+
+    macro_rules! modify {
+        ($reg:ident) => {
+            afio.$reg.modify(|r, w| unsafe { w.bits(update(r.bits())) })
+        };
+    }
+
+    match (port, high) {
+        #[cfg(feature = "afio4")]
+        (0, _) => modify!(afio_gpacfgr),
+        #[cfg(feature = "afio4")]
+        (1, _) => modify!(afio_gpbcfgr),
+        #[cfg(any(feature = "ht32f1755", feature = "ht32f1765"))]
+        (2, _) => modify!(afio_gpccfgr),
+        #[cfg(any(feature = "ht32f1755", feature = "ht32f1765"))]
+        (3, _) => modify!(afio_gpdcfgr),
+        #[cfg(any(feature = "ht32f1755", feature = "ht32f1765"))]
+        (4, _) => modify!(afio_gpecfgr),
+
+        #[cfg(not(feature = "afio4"))]
+        (0, false) => modify!(afio_gpacfglr),
+        #[cfg(not(feature = "afio4"))]
+        (0, true) => modify!(afio_gpacfghr),
+        #[cfg(not(feature = "afio4"))]
+        (1, false) => modify!(afio_gpbcfglr),
+        #[cfg(not(feature = "afio4"))]
+        (1, true) => modify!(afio_gpbcfghr),
+        #[cfg(not(feature = "afio4"))]
+        (2, false) => modify!(afio_gpccfglr),
+        #[cfg(not(feature = "afio4"))]
+        (2, true) => modify!(afio_gpccfghr),
+        #[cfg(not(feature = "afio4"))]
+        (3, false) => modify!(afio_gpdcfglr),
+        #[cfg(not(any(feature = "afio4", feature = "ht32f1653", feature = "ht32f1654")))]
+        (3, true) => modify!(afio_gpdcfghr),
+        #[cfg(not(any(feature = "afio4", feature = "ht32f1653", feature = "ht32f1654")))]
+        (4, false) => modify!(afio_gpecfglr),
+        #[cfg(not(any(feature = "afio4", feature = "ht32f1653", feature = "ht32f1654")))]
+        (4, true) => modify!(afio_gpecfghr),
+
+        _ => unreachable!(),
+    }
+}
+
 macro_rules! afio {
     ($gpiox:ident: [
          $($PXi:ident: ($afio_gpxcfgr:ident, $pxcfgi:ident),)+
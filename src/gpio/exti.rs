@@ -0,0 +1,215 @@
+//! External Interrupt/Event Controller
+use crate::ckcu::{Clocks, Pcer};
+use crate::gpio::Afio;
+use crate::pac::{AFIO, EXTI};
+use crate::time::MicroSeconds;
+
+use vcell::VolatileCell;
+
+/// Constrained EXTI peripheral
+pub struct Exti {
+    inner: EXTI,
+}
+
+impl Exti {
+    pub fn new(exti: EXTI) -> Self {
+        // EXTI clock enable
+        exti.enable();
+
+        Self { inner: exti }
+    }
+
+    pub fn release(self) -> EXTI {
+        self.inner
+    }
+}
+
+/// Conditions that trigger an EXTI interrupt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    /// Trigger while the pin is low
+    Low,
+    /// Trigger while the pin is high
+    High,
+    /// Trigger on a falling edge
+    Falling,
+    /// Trigger on a rising edge
+    Rising,
+    /// Trigger on both edges
+    RisingFalling,
+}
+
+impl Edge {
+    /// The SRCTYPE value for this condition
+    pub(crate) fn bits(self) -> u32 {
+        match self {
+            Edge::Low => 0b000,
+            Edge::High => 0b001,
+            Edge::Falling => 0b010,
+            Edge::Rising => 0b011,
+            Edge::RisingFalling => 0b100,
+        }
+    }
+}
+
+/// Maximum de-bounce counter value, DBCNT is 28 bit wide
+const DBCNT_MAX: u64 = (1 << 28) - 1;
+
+/// Errors that can occur while configuring the de-bounce time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebounceError {
+    /// The de-bounce time is shorter than one PCLK cycle
+    TooShort,
+    /// The de-bounce time does not fit into the de-bounce counter
+    TooLong,
+}
+
+/// A single EXTI channel, channel n is shared by pin n of every port
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtiChannel {
+    line: u8,
+}
+
+impl ExtiChannel {
+    /// Returns the channel number
+    pub fn number(&self) -> u8 {
+        self.line
+    }
+
+    /// Only report an edge once the pin kept its level for `time`.
+    ///
+    /// The de-bounce counter runs from PCLK, so `time` has to be at least
+    /// one PCLK cycle and at most 2^28 - 1 PCLK cycles long.
+    pub fn set_debounce(
+        &mut self,
+        _exti: &mut Exti,
+        time: MicroSeconds,
+        clocks: &Clocks,
+    ) -> Result<(), DebounceError> {
+        let cycles = time.ticks() as u64 * clocks.pclk().raw() as u64 / 1_000_000;
+        if cycles == 0 {
+            return Err(DebounceError::TooShort);
+        } else if cycles > DBCNT_MAX {
+            return Err(DebounceError::TooLong);
+        }
+
+        // DBEN is located in bit 31, DBCNT in bits 27:0
+        let cfgr = cfgr(self.line);
+        cfgr.set((cfgr.get() & !(DBCNT_MAX as u32)) | (1 << 31) | cycles as u32);
+        Ok(())
+    }
+
+    /// Report edges right away
+    pub fn disable_debounce(&mut self, _exti: &mut Exti) {
+        let cfgr = cfgr(self.line);
+        cfgr.set(cfgr.get() & !(1 << 31));
+    }
+}
+
+/// External interrupt pin
+pub trait ExtiPin {
+    /// Returns the EXTI channel of this pin
+    fn channel(&self) -> ExtiChannel;
+    /// Connect the EXTI channel of this pin number to the port of this pin
+    fn make_interrupt_source(&mut self, afio: &mut Afio);
+    /// Select the condition that triggers the interrupt
+    fn trigger_on_edge(&mut self, exti: &mut Exti, edge: Edge);
+    /// Enable the interrupt of the EXTI channel
+    fn enable_interrupt(&mut self, exti: &mut Exti);
+    /// Disable the interrupt of the EXTI channel
+    fn disable_interrupt(&mut self, exti: &mut Exti);
+    /// Clear the edge flag, this has to be done in the interrupt handler
+    /// or it will fire again right away
+    fn clear_interrupt_pending_bit(&mut self);
+    /// Check whether an edge was detected on the EXTI channel
+    fn check_interrupt(&self) -> bool;
+}
+
+// The helpers below are shared by the typed and the erased pins, `line` is
+// the pin number and therefore the EXTI channel.
+
+/// Select `port` as the source of the EXTI channel `line`
+pub(crate) fn make_interrupt_source(_afio: &mut Afio, port: u8, line: u8) {
+    set_interrupt_source(port, line)
+}
+
+/// Like [`make_interrupt_source`], for callers that own the EXTI channel
+/// but can not borrow `Afio`
+pub(crate) fn set_interrupt_source(port: u8, line: u8) {
+    // ESSR0 contains channel 0 - 7, ESSR1 channel 8 - 15, 4 bits each
+    let shift = (line % 8) * 4;
+    let mask = 0xF << shift;
+    let value = (port as u32) << shift;
+
+    // ESSR is shared with the channels armed by the async pins, which do
+    // not borrow `Afio`
+    cortex_m::interrupt::free(|_| {
+        let afio = unsafe { &*AFIO::ptr() };
+        if line < 8 {
+            afio.afio_essr0
+                .modify(|r, w| unsafe { w.bits((r.bits() & !mask) | value) });
+        } else {
+            afio.afio_essr1
+                .modify(|r, w| unsafe { w.bits((r.bits() & !mask) | value) });
+        }
+    });
+}
+
+/// The EXTI_CFGRn register of a channel, they are laid out one after
+/// another starting with EXTI_CFGR0
+pub(crate) fn cfgr(line: u8) -> &'static VolatileCell<u32> {
+    let exti = unsafe { &*EXTI::ptr() };
+    unsafe {
+        &*(&exti.exti_cfgr0 as *const crate::pac::exti::EXTI_CFGR0)
+            .cast::<VolatileCell<u32>>()
+            .add(line as usize)
+    }
+}
+
+pub(crate) fn channel(line: u8) -> ExtiChannel {
+    ExtiChannel { line }
+}
+
+pub(crate) fn trigger_on_edge(_exti: &mut Exti, line: u8, edge: Edge) {
+    // SRCTYPE is located in bits 30:28
+    let cfgr = cfgr(line);
+    cfgr.set((cfgr.get() & !(0b111 << 28)) | (edge.bits() << 28));
+}
+
+pub(crate) fn enable_interrupt(_exti: &mut Exti, line: u8) {
+    set_interrupt_enable(line, true)
+}
+
+pub(crate) fn disable_interrupt(_exti: &mut Exti, line: u8) {
+    set_interrupt_enable(line, false)
+}
+
+/// Enable or disable the interrupt of the EXTI channel `line`
+///
+/// EXTI_CR is shared by all channels and also changed from the interrupt
+/// handlers of the async pins, so it is only modified in a critical section.
+pub(crate) fn set_interrupt_enable(line: u8, enable: bool) {
+    cortex_m::interrupt::free(|_| {
+        let exti = unsafe { &*EXTI::ptr() };
+        exti.exti_cr.modify(|r, w| unsafe {
+            if enable {
+                w.bits(r.bits() | (1 << line))
+            } else {
+                w.bits(r.bits() & !(1 << line))
+            }
+        });
+    });
+}
+
+pub(crate) fn clear_interrupt_pending_bit(line: u8) {
+    // NOTE(unsafe) atomic write to a write 1 to clear register
+    let exti = unsafe { &*EXTI::ptr() };
+    exti.exti_edgeflgr.write(|w| unsafe { w.bits(1 << line) });
+    exti.exti_edgesr.write(|w| unsafe { w.bits(1 << line) });
+}
+
+pub(crate) fn check_interrupt(line: u8) -> bool {
+    // NOTE(unsafe) atomic read with no side effects
+    let exti = unsafe { &*EXTI::ptr() };
+    exti.exti_edgeflgr.read().bits() & (1 << line) != 0
+}
@@ -1,7 +1,7 @@
 use super::GpioRegExt;
 
-gpio_trait!(gpioa);
-gpio_trait!(gpiob);
+gpio_trait!(gpioa, 0, drvr);
+gpio_trait!(gpiob, 1, drvr);
 
 gpio!(GPIOA, gpioa, parst, paen, drvr, dircr, pur, pdr, iner, odr, [
     PA0: (pa0, 0, Input<Disabled>, AF0, dir0, pu0, pd0, inen0, od0),
@@ -1,10 +1,10 @@
 use super::GpioRegExt;
 
-gpio_trait!(gpioa);
-gpio_trait!(gpiob);
-gpio_trait!(gpioc);
-gpio_trait!(gpiod);
-gpio_trait!(gpioe);
+gpio_trait!(gpioa, 0, drvr);
+gpio_trait!(gpiob, 1, drvr);
+gpio_trait!(gpioc, 2, drvr);
+gpio_trait!(gpiod, 3, drvr);
+gpio_trait!(gpioe, 4, drvr);
 
 gpio!(GPIOA, gpioa, parst, paen, drvr, dircr, pur, pdr, iner, odr, [
     PA0: (pa0, 0, Input<Disabled>, AF0, dir0, pu0, pd0, inen0, od0),
@@ -1,10 +1,11 @@
 use super::GpioRegExt;
 
-gpio_trait!(gpioa);
-gpio_trait!(gpiob);
-gpio_trait!(gpioc);
-gpio_trait!(gpiod);
-gpio_trait!(gpioe);
+// Only GPIOA and GPIOE have a DRVR register
+gpio_trait!(gpioa, 0, drvr);
+gpio_trait!(gpiob, 1, _);
+gpio_trait!(gpioc, 2, _);
+gpio_trait!(gpiod, 3, _);
+gpio_trait!(gpioe, 4, drvr);
 
 gpio!(GPIOA, gpioa, parst, paen, drvr, dircr, pur, pdr, iner, odr, [
     PA0: (pa0, 0, Input<Disabled>, AF0, dir0, pu0, pd0, inen0, od0),
@@ -25,7 +26,7 @@ gpio!(GPIOA, gpioa, parst, paen, drvr, dircr, pur, pdr, iner, odr, [
     PA15: (pa15, 15, Input<Disabled>, AF0, dir15, pu15, pd15, inen15, od15),
 ]);
 
-gpio!(GPIOB, gpiob, pbrst, pben, drvr, dircr, pur, pdr, iner, odr, [
+gpio!(GPIOB, gpiob, pbrst, pben, _, dircr, pur, pdr, iner, odr, [
     PB0: (pb0, 0, Input<Disabled>, AF0, dir0, pu0, pd0, inen0, od0),
     PB1: (pb1, 1, Input<Disabled>, AF0, dir1, pu1, pd1, inen1, od1),
     PB2: (pb2, 2, Input<Disabled>, AF0, dir2, pu2, pd2, inen2, od2),
@@ -44,7 +45,7 @@ gpio!(GPIOB, gpiob, pbrst, pben, drvr, dircr, pur, pdr, iner, odr, [
     PB15: (pb15, 15, Input<Disabled>, AF0, dir15, pu15, pd15, inen15, od15),
 ]);
 
-gpio!(GPIOC, gpioc, pcrst, pcen, drvr, dircr, pur, pdr, iner, odr, [
+gpio!(GPIOC, gpioc, pcrst, pcen, _, dircr, pur, pdr, iner, odr, [
     PC0: (pc0, 0, Input<Disabled>, AF0, dir0, pu0, pd0, inen0, od0),
     PC1: (pc1, 1, Input<Disabled>, AF0, dir1, pu1, pd1, inen1, od1),
     PC2: (pc2, 2, Input<Disabled>, AF0, dir2, pu2, pd2, inen2, od2),
@@ -65,7 +66,7 @@ gpio!(GPIOC, gpioc, pcrst, pcen, drvr, dircr, pur, pdr, iner, odr, [
     PC15: (pc15, 15, Input<Disabled>, AF0, dir15, pu15, pd15, inen15, od15),
 ]);
 
-gpio!(GPIOD, gpiod, pdrst, pden, drvr, dircr, pur, pdr, iner, odr, [
+gpio!(GPIOD, gpiod, pdrst, pden, _, dircr, pur, pdr, iner, odr, [
     PD0: (pd0, 0, Input<Disabled>, AF0, dir0, pu0, pd0, inen0, od0),
     PD1: (pd1, 1, Input<Disabled>, AF0, dir1, pu1, pd1, inen1, od1),
     PD2: (pd2, 2, Input<Disabled>, AF0, dir2, pu2, pd2, inen2, od2),
@@ -1,7 +1,13 @@
 //! General Purpose Input / Output
 
-mod afio;
-pub use afio::Afio;
+pub(crate) mod afio;
+pub use afio::{AfNumber, Afio};
+
+mod exti;
+pub use exti::{DebounceError, Edge, Exti, ExtiChannel, ExtiPin};
+
+#[cfg(feature = "async")]
+pub mod wait;
 
 use core::convert::Infallible;
 use core::marker::PhantomData;
@@ -21,6 +27,15 @@ trait GpioRegExt {
     fn is_set_low(&self, pos: u8) -> bool;
     fn set_high(&self, pos: u8);
     fn set_low(&self, pos: u8);
+    fn port_index(&self) -> u8;
+    fn set_drive_strength(&self, pos: u8, current: GpioCurrent) -> Result<(), DriveStrengthError>;
+    fn mode(&self, pos: u8) -> DynamicMode;
+    fn set_mode(&self, pos: u8, mode: DynamicMode);
+    fn set_input_enable(&self, pos: u8, enable: bool) -> bool;
+    fn write_port(&self, set: u16, reset: u16);
+    fn read_port(&self) -> u16;
+    fn lock(&self, mask: u16) -> bool;
+    fn locked(&self) -> u16;
 }
 
 /// Output mode (type state)
@@ -55,6 +70,65 @@ pub struct Floating;
 /// any data.
 pub struct Disabled;
 
+/// Analog mode (type state)
+/// The digital input and the pull resistors are disabled, the pin is
+/// connected to the ADC input through its AF
+pub struct Analog;
+
+/// The alternate function that connects a pin to its ADC input
+#[cfg(not(feature = "afio4"))]
+pub type AnalogAf = AF2;
+/// The alternate function that connects a pin to its ADC input
+#[cfg(feature = "afio4")]
+pub type AnalogAf = AF0;
+
+/// Dynamic mode (type state)
+/// The pin can be switched between input and output at runtime, the
+/// current mode is kept in the GPIO registers
+pub struct Dynamic;
+
+/// The modes a [`Dynamic`] pin can be in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DynamicMode {
+    InputFloating,
+    InputPullUp,
+    InputPullDown,
+    OutputPushPull,
+    OutputOpenDrain,
+}
+
+impl DynamicMode {
+    /// Whether the pin level can be read in this mode
+    fn is_input(self) -> bool {
+        !matches!(self, DynamicMode::OutputPushPull)
+    }
+
+    /// Whether the pin is driven in this mode
+    fn is_output(self) -> bool {
+        matches!(self, DynamicMode::OutputPushPull | DynamicMode::OutputOpenDrain)
+    }
+}
+
+/// Error for calling a method that is not possible in the current
+/// [`DynamicMode`] of the pin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinModeError {
+    IncorrectMode,
+}
+
+impl crate::hal::digital::Error for PinModeError {
+    fn kind(&self) -> crate::hal::digital::ErrorKind {
+        crate::hal::digital::ErrorKind::Other
+    }
+}
+
+/// Error for setting the drive strength of a pin on a port without a DRVR
+/// register, such as GPIOB to GPIOD on the HT32F175x
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriveStrengthError {
+    Unsupported,
+}
+
 /// Alternate function 0 (type state)
 pub struct AF0;
 /// Alternate function 1 (type state)
@@ -88,26 +162,261 @@ pub struct AF14;
 /// Alternate function 15 (type state)
 pub struct AF15;
 
-/// The 4 current values that can be used for output pins
+/// The current values that can be used for output pins
 /// TODO: Migrate these into the PAC and re-export them here in order to avoid
 /// API breaking.
 #[derive(Copy, Clone, Debug)]
 pub enum GpioCurrent {
     MA4,
     MA8,
+    #[cfg(any(
+        feature = "ht32f1653",
+        feature = "ht32f1654",
+        feature = "ht32f1655",
+        feature = "ht32f1656",
+    ))]
+    MA12,
+    #[cfg(any(
+        feature = "ht32f1653",
+        feature = "ht32f1654",
+        feature = "ht32f1655",
+        feature = "ht32f1656",
+    ))]
+    MA16,
 }
 
+/// Width of the drive current setting of a single pin in DRVR
+///
+/// The HT32F1653/1654/1655/1656 User Manual, section "Port x Output Current
+/// Drive Selection Register (PxDRVR)", lists two DVn bits per pin for all
+/// 16 pins, selecting 4, 8, 12 or 16 mA. The HT32F125x and HT32F175x use a
+/// single bit per pin to select 4 or 8 mA.
+///
+/// The PAC can't be used for this: its DRVR has the single-bit fields DV0
+/// to DV7 on every family, so it doesn't even cover the upper eight pins
+/// of a port. DRVR is therefore written as raw bits.
+#[cfg(any(
+    feature = "ht32f1653",
+    feature = "ht32f1654",
+    feature = "ht32f1655",
+    feature = "ht32f1656",
+))]
+const DRVR_WIDTH: u8 = 2;
+#[cfg(not(any(
+    feature = "ht32f1653",
+    feature = "ht32f1654",
+    feature = "ht32f1655",
+    feature = "ht32f1656",
+)))]
+const DRVR_WIDTH: u8 = 1;
+
 impl GpioCurrent {
-    // TODO
-    #[allow(dead_code)]
     fn to_bits(self) -> u8 {
         match self {
-            Self::MA4 => 0b0,
-            Self::MA8 => 0b1,
+            Self::MA4 => 0b00,
+            Self::MA8 => 0b01,
+            #[cfg(any(
+                feature = "ht32f1653",
+                feature = "ht32f1654",
+                feature = "ht32f1655",
+                feature = "ht32f1656",
+            ))]
+            Self::MA12 => 0b10,
+            #[cfg(any(
+                feature = "ht32f1653",
+                feature = "ht32f1654",
+                feature = "ht32f1655",
+                feature = "ht32f1656",
+            ))]
+            Self::MA16 => 0b11,
+        }
+    }
+
+    /// Update the DRVR value `drvr` with this current for pin `pos`
+    fn update_drvr(self, drvr: u32, pos: u8) -> u32 {
+        let shift = pos * DRVR_WIDTH;
+        let mask = ((1 << DRVR_WIDTH) - 1) << shift;
+        (drvr & !mask) | ((self.to_bits() as u32) << shift)
+    }
+}
+
+/// A pin that belongs to the GPIO port `Port`
+pub trait PortPin {
+    /// The GPIO peripheral of the port
+    type Port;
+    /// The pin number within the port
+    const INDEX: u8;
+    #[doc(hidden)]
+    fn port() -> PortRegs;
+}
+
+/// The registers of a GPIO port
+#[doc(hidden)]
+pub struct PortRegs(*const dyn GpioRegExt);
+
+/// Pins that all belong to the GPIO port `PORT`
+pub trait PinGroup<PORT> {
+    /// Mask of the pin numbers in the group
+    const MASK: u16;
+    #[doc(hidden)]
+    fn port() -> PortRegs;
+}
+
+macro_rules! pin_group {
+    ($P0:ident $(, $P:ident)*) => {
+        impl<PORT, $P0: PortPin<Port = PORT>, $($P: PortPin<Port = PORT>),*> PinGroup<PORT>
+            for ($P0, $($P,)*)
+        {
+            const MASK: u16 = (1 << $P0::INDEX) $(| (1 << $P::INDEX))*;
+
+            fn port() -> PortRegs {
+                $P0::port()
+            }
+        }
+    };
+}
+
+pin_group!(P0);
+pin_group!(P0, P1);
+pin_group!(P0, P1, P2);
+pin_group!(P0, P1, P2, P3);
+pin_group!(P0, P1, P2, P3, P4);
+pin_group!(P0, P1, P2, P3, P4, P5);
+pin_group!(P0, P1, P2, P3, P4, P5, P6);
+pin_group!(P0, P1, P2, P3, P4, P5, P6, P7);
+pin_group!(P0, P1, P2, P3, P4, P5, P6, P7, P8);
+pin_group!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9);
+pin_group!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10);
+pin_group!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11);
+pin_group!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12);
+pin_group!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12, P13);
+pin_group!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12, P13, P14);
+pin_group!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12, P13, P14, P15);
+
+/// Several pins of the same GPIO port, which are written and read at once
+///
+/// Bit n of masks and values corresponds to pin n of the port. Bits of
+/// pins that are not part of the group are ignored.
+pub struct PortGroup<PORT, PINS> {
+    pins: PINS,
+    port: *const dyn GpioRegExt,
+    _port: PhantomData<PORT>,
+}
+
+// NOTE(unsafe) The only write access is to SRR, which is thread safe
+unsafe impl<PORT, PINS: Send> Send for PortGroup<PORT, PINS> {}
+
+impl<PORT, PINS: PinGroup<PORT>> PortGroup<PORT, PINS> {
+    /// Group a tuple of pins, which all have to belong to the same port
+    pub fn new(pins: PINS) -> Self {
+        Self {
+            pins,
+            port: PINS::port().0,
+            _port: PhantomData,
+        }
+    }
+
+    /// Returns the mask of the pin numbers in the group
+    pub fn mask(&self) -> u16 {
+        PINS::MASK
+    }
+
+    /// Drive the pins in `mask` to the levels in `value` with a single
+    /// atomic write. This only has an effect on output pins.
+    pub fn write(&mut self, mask: u16, value: u16) {
+        let mask = mask & PINS::MASK;
+        unsafe { (*self.port).write_port(value & mask, !value & mask) }
+    }
+
+    /// Read the levels of all pins of the group at once
+    pub fn read(&self) -> u16 {
+        unsafe { (*self.port).read_port() & PINS::MASK }
+    }
+
+    /// Release the pins
+    pub fn release(self) -> PINS {
+        self.pins
+    }
+}
+
+impl<PORT, PINS: PinGroup<PORT>> PortGroup<PORT, PINS> {
+    /// Returns whether the configuration of every pin in the group is locked
+    pub fn is_locked(&self) -> bool {
+        unsafe { (*self.port).locked() & PINS::MASK == PINS::MASK }
+    }
+
+    /// Lock the configuration of all pins in the group until the next
+    /// reset, this fails if the port was locked before, see [`Locked`]
+    pub fn lock(self) -> Result<Locked<Self>, Self> {
+        if unsafe { (*self.port).lock(PINS::MASK) } {
+            Ok(Locked { inner: self })
+        } else {
+            Err(self)
         }
     }
 }
 
+impl<PORT, PINS: PinGroup<PORT>> Locked<PortGroup<PORT, PINS>> {
+    /// See [`PortGroup::write`]
+    pub fn write(&mut self, mask: u16, value: u16) {
+        self.inner.write(mask, value)
+    }
+
+    /// See [`PortGroup::read`]
+    pub fn read(&self) -> u16 {
+        self.inner.read()
+    }
+}
+
+/// A pin, or group of pins, whose configuration is locked until the next
+/// reset.
+///
+/// The pin levels can still be read and written, but the mode, pull and
+/// alternate function can not be changed anymore.
+///
+/// Locking is one-shot per port: the lock register of a port only accepts
+/// a single write until the next reset. After a pin or [`PortGroup`] of a
+/// port was locked, `lock` on any other pin of that port fails and hands
+/// the pin back. Pins of one port that all need to be locked have to be
+/// locked together through a [`PortGroup`].
+pub struct Locked<P> {
+    inner: P,
+}
+
+impl<P: ErrorType> ErrorType for Locked<P> {
+    type Error = P::Error;
+}
+
+impl<P: InputPin> InputPin for Locked<P> {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        self.inner.is_high()
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        self.inner.is_low()
+    }
+}
+
+impl<P: OutputPin> OutputPin for Locked<P> {
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.inner.set_high()
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.inner.set_low()
+    }
+}
+
+impl<P: StatefulOutputPin> StatefulOutputPin for Locked<P> {
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        self.inner.is_set_high()
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        self.inner.is_set_low()
+    }
+}
+
 /// Fully erased pin
 pub struct Pin<MODE> {
     i: u8,
@@ -172,13 +481,295 @@ impl<MODE> InputPin for Pin<Input<MODE>> {
     }
 }
 
-impl<MODE> ErrorType for Pin<MODE> {
+impl<MODE> Pin<MODE> {
+    /// Returns the index of the port, 0 for GPIOA, 1 for GPIOB and so on
+    pub fn port_index(&self) -> u8 {
+        unsafe { (*self.port).port_index() }
+    }
+
+    /// Returns the pin number within the port
+    pub fn pin_index(&self) -> u8 {
+        self.i
+    }
+
+    /// Change the AF of the pin, leave the IO mode alone though
+    pub fn set_alternate(&mut self, afio: &mut Afio, af: AfNumber) {
+        afio::set_af(afio, self.port_index(), self.i, af)
+    }
+
+    /// Enable or disable the input buffer of the pin, returns whether it
+    /// was enabled before
+    pub(crate) fn set_input_enable(&mut self, enable: bool) -> bool {
+        unsafe { (*self.port).set_input_enable(self.i, enable) }
+    }
+
+    /// Change the pin into the given mode
+    fn into_mode<NEW>(self, mode: DynamicMode) -> Pin<NEW> {
+        unsafe { (*self.port).set_mode(self.i, mode) };
+        Pin {
+            i: self.i,
+            port: self.port,
+            _mode: PhantomData,
+        }
+    }
+
+    /// Change the pin to an output pin in push pull mode
+    pub fn into_output_push_pull(self) -> Pin<Output<PushPull>> {
+        self.into_mode(DynamicMode::OutputPushPull)
+    }
+
+    /// Change the pin to an output pin in open drain mode
+    pub fn into_output_open_drain(self) -> Pin<Output<OpenDrain>> {
+        self.into_mode(DynamicMode::OutputOpenDrain)
+    }
+
+    /// Change the pin into an input pin in pull up mode
+    pub fn into_input_pull_up(self) -> Pin<Input<PullUp>> {
+        self.into_mode(DynamicMode::InputPullUp)
+    }
+
+    /// Change the pin into an input pin in pull down mode
+    pub fn into_input_pull_down(self) -> Pin<Input<PullDown>> {
+        self.into_mode(DynamicMode::InputPullDown)
+    }
+
+    /// Change the pin into an input pin in floating mode
+    pub fn into_input_floating(self) -> Pin<Input<Floating>> {
+        self.into_mode(DynamicMode::InputFloating)
+    }
+
+    /// Change the pin into a pin whose mode can be switched at runtime,
+    /// starting out as input pin in floating mode
+    pub fn into_dynamic(self) -> Pin<Dynamic> {
+        self.into_mode(DynamicMode::InputFloating)
+    }
+
+    /// Returns whether the configuration of the pin is locked
+    pub fn is_locked(&self) -> bool {
+        unsafe { (*self.port).locked() & (1 << self.i) != 0 }
+    }
+
+    /// Lock the configuration of the pin until the next reset, this fails
+    /// if its port was locked before, see [`Locked`]
+    pub fn lock(self) -> Result<Locked<Self>, Self> {
+        if unsafe { (*self.port).lock(1 << self.i) } {
+            Ok(Locked { inner: self })
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl<MODE> ErrorType for Pin<Input<MODE>> {
     type Error = Infallible;
 }
 
+impl<MODE> ErrorType for Pin<Output<MODE>> {
+    type Error = Infallible;
+}
+
+impl ErrorType for Pin<Dynamic> {
+    type Error = PinModeError;
+}
+
+impl Pin<Dynamic> {
+    /// Returns the current mode of the pin
+    pub fn mode(&self) -> DynamicMode {
+        unsafe { (*self.port).mode(self.i) }
+    }
+
+    /// Switch the pin into another mode
+    pub fn set_mode(&mut self, mode: DynamicMode) {
+        unsafe { (*self.port).set_mode(self.i, mode) }
+    }
+
+    /// Switch the pin into an input pin in floating mode
+    pub fn make_input_floating(&mut self) {
+        self.set_mode(DynamicMode::InputFloating)
+    }
+
+    /// Switch the pin into an input pin in pull up mode
+    pub fn make_input_pull_up(&mut self) {
+        self.set_mode(DynamicMode::InputPullUp)
+    }
+
+    /// Switch the pin into an input pin in pull down mode
+    pub fn make_input_pull_down(&mut self) {
+        self.set_mode(DynamicMode::InputPullDown)
+    }
+
+    /// Switch the pin into an output pin in push pull mode
+    pub fn make_push_pull_output(&mut self) {
+        self.set_mode(DynamicMode::OutputPushPull)
+    }
+
+    /// Switch the pin into an output pin in open drain mode
+    pub fn make_open_drain_output(&mut self) {
+        self.set_mode(DynamicMode::OutputOpenDrain)
+    }
+}
+
+impl InputPin for Pin<Dynamic> {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        self.is_low().map(|v| !v)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        if self.mode().is_input() {
+            Ok(unsafe { (*self.port).is_low(self.i) })
+        } else {
+            Err(PinModeError::IncorrectMode)
+        }
+    }
+}
+
+impl OutputPin for Pin<Dynamic> {
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        if self.mode().is_output() {
+            unsafe { (*self.port).set_high(self.i) };
+            Ok(())
+        } else {
+            Err(PinModeError::IncorrectMode)
+        }
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        if self.mode().is_output() {
+            unsafe { (*self.port).set_low(self.i) };
+            Ok(())
+        } else {
+            Err(PinModeError::IncorrectMode)
+        }
+    }
+}
+
+impl StatefulOutputPin for Pin<Dynamic> {
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        self.is_set_low().map(|v| !v)
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        if self.mode().is_output() {
+            Ok(unsafe { (*self.port).is_set_low(self.i) })
+        } else {
+            Err(PinModeError::IncorrectMode)
+        }
+    }
+}
+
+// Waiting on the level makes use of the level conditions of the EXTI, which
+// trigger right away if the pin already is at that level
+#[cfg(feature = "async")]
+impl<MODE> embedded_hal_async::digital::Wait for Pin<Input<MODE>> {
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        wait::ExtiFuture::new(unsafe { (*self.port).port_index() }, self.i, Edge::High).await;
+        Ok(())
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        wait::ExtiFuture::new(unsafe { (*self.port).port_index() }, self.i, Edge::Low).await;
+        Ok(())
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        wait::ExtiFuture::new(unsafe { (*self.port).port_index() }, self.i, Edge::Rising).await;
+        Ok(())
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        wait::ExtiFuture::new(unsafe { (*self.port).port_index() }, self.i, Edge::Falling).await;
+        Ok(())
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        wait::ExtiFuture::new(unsafe { (*self.port).port_index() }, self.i, Edge::RisingFalling).await;
+        Ok(())
+    }
+}
+
+impl<MODE> Pin<Output<MODE>> {
+    /// Set the maximum current the pin can source or sink
+    ///
+    /// Returns an error if the port of the pin has no DRVR register
+    pub fn set_drive_strength(&mut self, current: GpioCurrent) -> Result<(), DriveStrengthError> {
+        unsafe { (*self.port).set_drive_strength(self.i, current) }
+    }
+}
+
+impl<MODE> ExtiPin for Pin<Input<MODE>> {
+    fn channel(&self) -> ExtiChannel {
+        exti::channel(self.i)
+    }
+
+    fn make_interrupt_source(&mut self, afio: &mut Afio) {
+        exti::make_interrupt_source(afio, unsafe { (*self.port).port_index() }, self.i)
+    }
+
+    fn trigger_on_edge(&mut self, exti: &mut Exti, edge: Edge) {
+        exti::trigger_on_edge(exti, self.i, edge)
+    }
+
+    fn enable_interrupt(&mut self, exti: &mut Exti) {
+        exti::enable_interrupt(exti, self.i)
+    }
+
+    fn disable_interrupt(&mut self, exti: &mut Exti) {
+        exti::disable_interrupt(exti, self.i)
+    }
+
+    fn clear_interrupt_pending_bit(&mut self) {
+        exti::clear_interrupt_pending_bit(self.i)
+    }
+
+    fn check_interrupt(&self) -> bool {
+        exti::check_interrupt(self.i)
+    }
+}
+
 // TODO: change the register names in the PAC, so we don't have to pass DINR, ODR, SRR, RR
+// `_` in place of the DRVR register name marks a port with a fixed drive
+// current
+macro_rules! drive_strength {
+    (_) => {
+        fn set_drive_strength(
+            &self,
+            _pos: u8,
+            _current: crate::gpio::GpioCurrent,
+        ) -> Result<(), crate::gpio::DriveStrengthError> {
+            Err(crate::gpio::DriveStrengthError::Unsupported)
+        }
+    };
+    ($drvr:ident) => {
+        fn set_drive_strength(
+            &self,
+            pos: u8,
+            current: crate::gpio::GpioCurrent,
+        ) -> Result<(), crate::gpio::DriveStrengthError> {
+            // NOTE(unsafe) only the bits of this pin are changed
+            self.$drvr.modify(|r, w| unsafe { w.bits(current.update_drvr(r.bits(), pos)) });
+            Ok(())
+        }
+    };
+}
+
+// Only pins of a port with a DRVR register can have their drive strength
+// set while they are being converted
+macro_rules! drive_strength_pin {
+    (_, $PXi:ident) => {};
+    ($drvr:ident, $PXi:ident) => {
+        impl<OUTPUT, AF> $PXi<Output<OUTPUT>, AF> {
+            /// Set the maximum current the pin can source or sink
+            pub fn with_drive_strength(mut self, current: crate::gpio::GpioCurrent) -> Self {
+                // The port has a DRVR register, this can not fail
+                self.set_drive_strength(current).ok();
+                self
+            }
+        }
+    };
+}
+
 macro_rules! gpio_trait {
-    ($gpiox:ident) => {
+    ($gpiox:ident, $port:expr, $drvr:tt) => {
         impl GpioRegExt for crate::pac::$gpiox::RegisterBlock {
             fn is_low(&self, pos: u8) -> bool {
                 // NOTE(unsafe) atomic read with no side effects
@@ -199,12 +790,109 @@ macro_rules! gpio_trait {
                 // NOTE(unsafe) atomic write to a stateless register
                 unsafe { self.rr.write(|w| w.bits(1 << pos)) };
             }
+
+            fn port_index(&self) -> u8 {
+                $port
+            }
+
+            drive_strength!($drvr);
+
+            fn write_port(&self, set: u16, reset: u16) {
+                // NOTE(unsafe) atomic write to a stateless register, SRR sets
+                // with its lower and resets with its upper half
+                unsafe { self.srr.write(|w| w.bits(set as u32 | ((reset as u32) << 16))) };
+            }
+
+            fn read_port(&self) -> u16 {
+                // NOTE(unsafe) atomic read with no side effects
+                self.dinr.read().bits() as u16
+            }
+
+            fn lock(&self, mask: u16) -> bool {
+                // The lock bits only take effect when written together with
+                // the lock key, once LKEY is set LOCKR ignores all writes
+                // until the next reset
+                if self.lockr.read().bits() & (1 << 16) != 0 {
+                    return false;
+                }
+                unsafe { self.lockr.write(|w| w.bits(0x5FA0_0000 | mask as u32)) };
+                self.locked() & mask == mask
+            }
+
+            fn locked(&self) -> u16 {
+                // LKEY reads as 1 once the lock is active
+                let lockr = self.lockr.read().bits();
+                if lockr & (1 << 16) != 0 {
+                    lockr as u16
+                } else {
+                    0
+                }
+            }
+
+            fn mode(&self, pos: u8) -> crate::gpio::DynamicMode {
+                use crate::gpio::DynamicMode;
+
+                let bit = |bits: u32| bits & (1 << pos) != 0;
+
+                if bit(self.dircr.read().bits()) {
+                    if bit(self.odr.read().bits()) {
+                        DynamicMode::OutputOpenDrain
+                    } else {
+                        DynamicMode::OutputPushPull
+                    }
+                } else if bit(self.pur.read().bits()) {
+                    DynamicMode::InputPullUp
+                } else if bit(self.pdr.read().bits()) {
+                    DynamicMode::InputPullDown
+                } else {
+                    DynamicMode::InputFloating
+                }
+            }
+
+            fn set_mode(&self, pos: u8, mode: crate::gpio::DynamicMode) {
+                use crate::gpio::DynamicMode;
+
+                let (dir, od, pu, pd) = match mode {
+                    DynamicMode::InputFloating => (false, false, false, false),
+                    DynamicMode::InputPullUp => (false, false, true, false),
+                    DynamicMode::InputPullDown => (false, false, false, true),
+                    DynamicMode::OutputPushPull => (true, false, false, false),
+                    DynamicMode::OutputOpenDrain => (true, true, false, false),
+                };
+                let update = |bits: u32, set: bool| {
+                    if set { bits | (1 << pos) } else { bits & !(1 << pos) }
+                };
+
+                // NOTE(unsafe) only the bits of this pin are changed
+                unsafe {
+                    self.pur.modify(|r, w| w.bits(update(r.bits(), pu)));
+                    self.pdr.modify(|r, w| w.bits(update(r.bits(), pd)));
+                    self.odr.modify(|r, w| w.bits(update(r.bits(), od)));
+                    // Open drain outputs keep the input function enabled,
+                    // so they can be read back as well
+                    self.iner.modify(|r, w| w.bits(update(r.bits(), mode.is_input() || od)));
+                    self.dircr.modify(|r, w| w.bits(update(r.bits(), dir)));
+                }
+            }
+
+            fn set_input_enable(&self, pos: u8, enable: bool) -> bool {
+                let enabled = self.iner.read().bits() & (1 << pos) != 0;
+                // NOTE(unsafe) only the bit of this pin is changed
+                self.iner.modify(|r, w| unsafe {
+                    if enable {
+                        w.bits(r.bits() | (1 << pos))
+                    } else {
+                        w.bits(r.bits() & !(1 << pos))
+                    }
+                });
+                enabled
+            }
         }
     };
 }
 
 macro_rules! gpio {
-    ($GPIOX:ident, $gpiox:ident, $pxrst:ident, $pxen:ident, $drvr:ident, $dircr:ident, $pur:ident, $pdr:ident, $iner: ident, $odr:ident, [
+    ($GPIOX:ident, $gpiox:ident, $pxrst:ident, $pxen:ident, $drvr:tt, $dircr:ident, $pur:ident, $pdr:ident, $iner: ident, $odr:ident, [
          $($PXi:ident: ($pxi:ident, $i:expr, $MODE:ty, $AF:ty, $dirx:ident, $pux: ident, $pdx:ident, $inenx:ident, $odx:ident),)+
     ]) => {
         pub mod $gpiox {
@@ -216,8 +904,10 @@ macro_rules! gpio {
             use crate::ckcu::Pcer;
 
             use crate::gpio::{Output, Input, OpenDrain, PushPull, PullDown, PullUp, Floating, Disabled};
-            use crate::gpio::{GpioExt, GpioRegExt, Pin};
-            use crate::gpio::afio::{Afio, AfioCfg};
+            use crate::gpio::{Analog, AnalogAf, Dynamic, DynamicMode, PinModeError};
+            use crate::gpio::{GpioExt, GpioRegExt, Locked, Pin, PortPin, PortRegs};
+            use crate::gpio::afio::{AfNumber, Afio, AfioCfg};
+            use crate::gpio::exti::{self, Edge, Exti, ExtiChannel, ExtiPin};
 
             use crate::gpio::{AF0, AF1, AF2, AF3};
             #[cfg(not(feature = "afio4"))]
@@ -307,6 +997,35 @@ macro_rules! gpio {
                         $PXi { _mode: PhantomData, _af: PhantomData }
                     }
 
+                    /// Change the pin into an analog input for the ADC
+                    ///
+                    /// Only the ADC function of the pin is selected. On the HT32F165x the
+                    /// comparator and OPA inputs sit on AF3 instead, select it with
+                    /// [`into_alternate_af3`](Self::into_alternate_af3) after this.
+                    pub fn into_analog(self, afio: &mut Afio) -> $PXi<Analog, AnalogAf> {
+                        // Set the direction to input
+                        (unsafe { &*$GPIOX::ptr() }).$dircr.modify(|_, w| w.$dirx().clear_bit());
+                        // Disable pull up
+                        (unsafe { &*$GPIOX::ptr() }).$pur.modify(|_, w| w.$pux().clear_bit());
+                        // Disable pull down
+                        (unsafe { &*$GPIOX::ptr() }).$pdr.modify(|_, w| w.$pdx().clear_bit());
+                        // Disable the Schmitt trigger, so the analog level does not cause
+                        // any leakage through the digital input
+                        (unsafe { &*$GPIOX::ptr() }).$iner.modify(|_, w| w.$inenx().clear_bit());
+                        // Select the ADC function
+                        self.into_alternate(afio, AfNumber::ADC.get());
+
+                        $PXi { _mode: PhantomData, _af: PhantomData }
+                    }
+
+                    /// Change the pin into a pin whose mode can be switched at runtime,
+                    /// starting out as input pin in floating mode
+                    pub fn into_dynamic(self) -> $PXi<Dynamic, AF> {
+                        unsafe { (*$GPIOX::ptr()).set_mode($i, DynamicMode::InputFloating) };
+
+                        $PXi { _mode: PhantomData, _af: PhantomData }
+                    }
+
                     /// Change the pin into an input pin in floating mode
                     pub fn into_input_floating(self) -> $PXi<Input<Floating>, AF> {
                         // Set the direction to input
@@ -436,6 +1155,46 @@ macro_rules! gpio {
                     }
                 }
 
+                impl<MODE, AF> $PXi<MODE, AF> {
+                    /// Returns whether the configuration of the pin is locked
+                    pub fn is_locked(&self) -> bool {
+                        unsafe { (*$GPIOX::ptr()).locked() & (1 << $i) != 0 }
+                    }
+
+                    /// Lock the configuration of the pin until the next reset, this
+                    /// fails if its port was locked before, see [`Locked`]
+                    pub fn lock(self) -> Result<Locked<Self>, Self> {
+                        if unsafe { (*$GPIOX::ptr()).lock(1 << $i) } {
+                            Ok(Locked { inner: self })
+                        } else {
+                            Err(self)
+                        }
+                    }
+                }
+
+                impl<MODE, AF> PortPin for $PXi<MODE, AF> {
+                    type Port = $GPIOX;
+                    const INDEX: u8 = $i;
+
+                    fn port() -> PortRegs {
+                        PortRegs($GPIOX::ptr() as *const dyn GpioRegExt)
+                    }
+                }
+
+                impl<OUTPUT, AF> $PXi<Output<OUTPUT>, AF> {
+                    /// Set the maximum current the pin can source or sink
+                    ///
+                    /// Returns an error if the port of the pin has no DRVR register
+                    pub fn set_drive_strength(
+                        &mut self,
+                        current: crate::gpio::GpioCurrent,
+                    ) -> Result<(), crate::gpio::DriveStrengthError> {
+                        unsafe { (*$GPIOX::ptr()).set_drive_strength($i, current) }
+                    }
+                }
+
+                drive_strength_pin!($drvr, $PXi);
+
                 impl<OUTPUT, AF> OutputPin for $PXi<Output<OUTPUT>, AF> {
                     fn set_high(&mut self) -> Result<(), Self::Error> {
                         Ok(unsafe { (*$GPIOX::ptr()).set_high($i) })
@@ -467,9 +1226,151 @@ macro_rules! gpio {
                     }
                 }
 
-                impl<MODE, AF> ErrorType for $PXi<MODE, AF> {
+                impl<INPUT, AF> ErrorType for $PXi<Input<INPUT>, AF> {
+                    type Error = Infallible;
+                }
+
+                impl<OUTPUT, AF> ErrorType for $PXi<Output<OUTPUT>, AF> {
                     type Error = Infallible;
                 }
+
+                impl<AF> ErrorType for $PXi<Dynamic, AF> {
+                    type Error = PinModeError;
+                }
+
+                impl<AF> $PXi<Dynamic, AF> {
+                    /// The erased pin shares the mode checks
+                    fn erased(&self) -> Pin<Dynamic> {
+                        Pin {
+                            i: $i,
+                            port: $GPIOX::ptr() as *const dyn GpioRegExt,
+                            _mode: PhantomData,
+                        }
+                    }
+
+                    /// Returns the current mode of the pin
+                    pub fn mode(&self) -> DynamicMode {
+                        unsafe { (*$GPIOX::ptr()).mode($i) }
+                    }
+
+                    /// Switch the pin into another mode
+                    pub fn set_mode(&mut self, mode: DynamicMode) {
+                        unsafe { (*$GPIOX::ptr()).set_mode($i, mode) }
+                    }
+
+                    /// Switch the pin into an input pin in floating mode
+                    pub fn make_input_floating(&mut self) {
+                        self.set_mode(DynamicMode::InputFloating)
+                    }
+
+                    /// Switch the pin into an input pin in pull up mode
+                    pub fn make_input_pull_up(&mut self) {
+                        self.set_mode(DynamicMode::InputPullUp)
+                    }
+
+                    /// Switch the pin into an input pin in pull down mode
+                    pub fn make_input_pull_down(&mut self) {
+                        self.set_mode(DynamicMode::InputPullDown)
+                    }
+
+                    /// Switch the pin into an output pin in push pull mode
+                    pub fn make_push_pull_output(&mut self) {
+                        self.set_mode(DynamicMode::OutputPushPull)
+                    }
+
+                    /// Switch the pin into an output pin in open drain mode
+                    pub fn make_open_drain_output(&mut self) {
+                        self.set_mode(DynamicMode::OutputOpenDrain)
+                    }
+                }
+
+                impl<AF> InputPin for $PXi<Dynamic, AF> {
+                    fn is_high(&mut self) -> Result<bool, Self::Error> {
+                        self.is_low().map(|v| !v)
+                    }
+
+                    fn is_low(&mut self) -> Result<bool, Self::Error> {
+                        self.erased().is_low()
+                    }
+                }
+
+                impl<AF> OutputPin for $PXi<Dynamic, AF> {
+                    fn set_high(&mut self) -> Result<(), Self::Error> {
+                        self.erased().set_high()
+                    }
+
+                    fn set_low(&mut self) -> Result<(), Self::Error> {
+                        self.erased().set_low()
+                    }
+                }
+
+                impl<AF> StatefulOutputPin for $PXi<Dynamic, AF> {
+                    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+                        self.is_set_low().map(|v| !v)
+                    }
+
+                    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+                        self.erased().is_set_low()
+                    }
+                }
+
+                #[cfg(feature = "async")]
+                impl<INPUT, AF> embedded_hal_async::digital::Wait for $PXi<Input<INPUT>, AF> {
+                    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+                        crate::gpio::wait::ExtiFuture::new(unsafe { (*$GPIOX::ptr()).port_index() }, $i, Edge::High).await;
+                        Ok(())
+                    }
+
+                    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+                        crate::gpio::wait::ExtiFuture::new(unsafe { (*$GPIOX::ptr()).port_index() }, $i, Edge::Low).await;
+                        Ok(())
+                    }
+
+                    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+                        crate::gpio::wait::ExtiFuture::new(unsafe { (*$GPIOX::ptr()).port_index() }, $i, Edge::Rising).await;
+                        Ok(())
+                    }
+
+                    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+                        crate::gpio::wait::ExtiFuture::new(unsafe { (*$GPIOX::ptr()).port_index() }, $i, Edge::Falling).await;
+                        Ok(())
+                    }
+
+                    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+                        crate::gpio::wait::ExtiFuture::new(unsafe { (*$GPIOX::ptr()).port_index() }, $i, Edge::RisingFalling).await;
+                        Ok(())
+                    }
+                }
+
+                impl<INPUT, AF> ExtiPin for $PXi<Input<INPUT>, AF> {
+                    fn channel(&self) -> ExtiChannel {
+                        exti::channel($i)
+                    }
+
+                    fn make_interrupt_source(&mut self, afio: &mut Afio) {
+                        exti::make_interrupt_source(afio, unsafe { (*$GPIOX::ptr()).port_index() }, $i)
+                    }
+
+                    fn trigger_on_edge(&mut self, exti: &mut Exti, edge: Edge) {
+                        exti::trigger_on_edge(exti, $i, edge)
+                    }
+
+                    fn enable_interrupt(&mut self, exti: &mut Exti) {
+                        exti::enable_interrupt(exti, $i)
+                    }
+
+                    fn disable_interrupt(&mut self, exti: &mut Exti) {
+                        exti::disable_interrupt(exti, $i)
+                    }
+
+                    fn clear_interrupt_pending_bit(&mut self) {
+                        exti::clear_interrupt_pending_bit($i)
+                    }
+
+                    fn check_interrupt(&self) -> bool {
+                        exti::check_interrupt($i)
+                    }
+                }
             )+
         }
     }
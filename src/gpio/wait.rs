@@ -0,0 +1,162 @@
+//! Async waiting on input pins, driven by the EXTI interrupts
+//!
+//! The EXTI interrupt handlers have to call [`on_interrupt`] for their
+//! channel. With the `exti-handlers` feature the HAL defines the `EXTI0` to
+//! `EXTI15` handlers itself, an application that defines any of them on its
+//! own then fails to link.
+//!
+//! Waiting on a pin unmasks the `EXTIn` interrupt of its channel in the
+//! NVIC, so its handler has to exist by then.
+use core::cell::RefCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+use cortex_m::interrupt::Mutex;
+use cortex_m::peripheral::NVIC;
+
+use crate::gpio::exti::{self, Edge};
+#[cfg(feature = "exti-handlers")]
+use crate::pac::interrupt;
+use crate::pac::{Interrupt, EXTI};
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_WAKER: Mutex<RefCell<Option<Waker>>> = Mutex::new(RefCell::new(None));
+/// The task waiting on each EXTI channel
+static WAKERS: [Mutex<RefCell<Option<Waker>>>; 16] = [NO_WAKER; 16];
+
+/// The interrupt of each EXTI channel
+const INTERRUPTS: [Interrupt; 16] = [
+    Interrupt::EXTI0,
+    Interrupt::EXTI1,
+    Interrupt::EXTI2,
+    Interrupt::EXTI3,
+    Interrupt::EXTI4,
+    Interrupt::EXTI5,
+    Interrupt::EXTI6,
+    Interrupt::EXTI7,
+    Interrupt::EXTI8,
+    Interrupt::EXTI9,
+    Interrupt::EXTI10,
+    Interrupt::EXTI11,
+    Interrupt::EXTI12,
+    Interrupt::EXTI13,
+    Interrupt::EXTI14,
+    Interrupt::EXTI15,
+];
+
+/// Resolves once the EXTI channel `line` triggered on its condition
+///
+/// The port of the pin is made the interrupt source of the channel and the
+/// interrupt of the channel is unmasked in the NVIC. AFIO and the EXTI have
+/// to be enabled with [`Afio::new`](super::Afio::new) and
+/// [`Exti::new`](super::Exti::new) before.
+pub(crate) struct ExtiFuture {
+    line: u8,
+}
+
+impl ExtiFuture {
+    pub(crate) fn new(port: u8, line: u8, edge: Edge) -> Self {
+        cortex_m::interrupt::free(|_| {
+            // The pin owns its channel, the critical section guards against
+            // the handlers of other channels
+            exti::set_interrupt_source(port, line);
+
+            // SRCTYPE is located in bits 30:28
+            let cfgr = exti::cfgr(line);
+            cfgr.set((cfgr.get() & !(0b111 << 28)) | (edge.bits() << 28));
+
+            // Drop edges seen before the wait started
+            exti::clear_interrupt_pending_bit(line);
+
+            exti::set_interrupt_enable(line, true);
+        });
+
+        // NOTE(unsafe) the handler only touches the state of its channel
+        unsafe { NVIC::unmask(INTERRUPTS[line as usize]) };
+
+        Self { line }
+    }
+
+    fn is_enabled(&self) -> bool {
+        let exti = unsafe { &*EXTI::ptr() };
+        exti.exti_cr.read().bits() & (1 << self.line) != 0
+    }
+}
+
+impl Future for ExtiFuture {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        // Register before checking, so a trigger in between is not missed
+        cortex_m::interrupt::free(|cs| {
+            WAKERS[self.line as usize]
+                .borrow(cs)
+                .replace(Some(cx.waker().clone()));
+        });
+
+        // The handler disables the channel once it triggered
+        if self.is_enabled() {
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    }
+}
+
+impl Drop for ExtiFuture {
+    fn drop(&mut self) {
+        cortex_m::interrupt::free(|cs| {
+            exti::set_interrupt_enable(self.line, false);
+            WAKERS[self.line as usize].borrow(cs).replace(None);
+        });
+    }
+}
+
+/// Handle the interrupt of EXTI channel `line`, this has to be called from
+/// the `EXTIn` interrupt handler unless the `exti-handlers` feature is on.
+///
+/// Disables the channel, so level conditions do not fire again right away,
+/// and wakes the waiting task.
+pub fn on_interrupt(line: u8) {
+    cortex_m::interrupt::free(|cs| {
+        exti::set_interrupt_enable(line, false);
+        exti::clear_interrupt_pending_bit(line);
+
+        if let Some(waker) = WAKERS[line as usize].borrow(cs).take() {
+            waker.wake();
+        }
+    });
+}
+
+#[cfg(feature = "exti-handlers")]
+macro_rules! exti_handlers {
+    ($($EXTIX:ident: $line:expr,)+) => {
+        $(
+            #[interrupt]
+            fn $EXTIX() {
+                on_interrupt($line);
+            }
+        )+
+    }
+}
+
+#[cfg(feature = "exti-handlers")]
+exti_handlers!(
+    EXTI0: 0,
+    EXTI1: 1,
+    EXTI2: 2,
+    EXTI3: 3,
+    EXTI4: 4,
+    EXTI5: 5,
+    EXTI6: 6,
+    EXTI7: 7,
+    EXTI8: 8,
+    EXTI9: 9,
+    EXTI10: 10,
+    EXTI11: 11,
+    EXTI12: 12,
+    EXTI13: 13,
+    EXTI14: 14,
+    EXTI15: 15,
+);
@@ -0,0 +1,150 @@
+//! Async I2C master, driven by the I2C interrupts
+//!
+//! The I2C interrupt handlers have to call [`on_interrupt`] for their
+//! peripheral. With the `i2c-handlers` feature the HAL defines the `I2C0`
+//! and `I2C1` handlers itself, which rules out driving
+//! [`Transfer`](super::Transfer) or [`I2cSlave`](super::I2cSlave) from
+//! those interrupts.
+//!
+//! An async transaction unmasks the interrupt of its peripheral in the
+//! NVIC, so the handler has to exist by then.
+use core::cell::RefCell;
+use core::future::Future;
+use core::ops::Deref;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+use cortex_m::interrupt::Mutex;
+use cortex_m::peripheral::NVIC;
+
+use super::{unlisten_transfer, Error, I2c, Machine, Operation, State};
+use crate::hal::i2c::AddressMode;
+#[cfg(feature = "i2c-handlers")]
+use crate::pac::interrupt;
+use crate::pac::{Interrupt, I2C0, I2C1};
+use crate::Sealed;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_WAKER: Mutex<RefCell<Option<Waker>>> = Mutex::new(RefCell::new(None));
+/// The task waiting on each I2C peripheral
+static WAKERS: [Mutex<RefCell<Option<Waker>>>; 2] = [NO_WAKER; 2];
+
+/// I2C peripherals that can be used asynchronously
+pub trait Instance: Sealed + Deref<Target = crate::pac::i2c0::RegisterBlock> {
+    #[doc(hidden)]
+    const INDEX: usize;
+    #[doc(hidden)]
+    const PTR: *const crate::pac::i2c0::RegisterBlock;
+    #[doc(hidden)]
+    const INTERRUPT: Interrupt;
+}
+
+impl Instance for I2C0 {
+    const INDEX: usize = 0;
+    const PTR: *const crate::pac::i2c0::RegisterBlock = I2C0::ptr();
+    const INTERRUPT: Interrupt = Interrupt::I2C0;
+}
+
+impl Instance for I2C1 {
+    const INDEX: usize = 1;
+    const PTR: *const crate::pac::i2c0::RegisterBlock = I2C1::ptr();
+    const INTERRUPT: Interrupt = Interrupt::I2C1;
+}
+
+/// Resolves once the transaction completed
+///
+/// The interrupt handler only masks the interrupts and wakes the task, the
+/// transaction is advanced when the future is polled.
+struct TransferFuture<'a, 'b, 'c, I2C: Instance, ADRM: AddressMode> {
+    i2c: &'c mut I2c<I2C, ADRM>,
+    machine: Machine<'a, 'b>,
+}
+
+impl<'a, 'b, 'c, I2C: Instance, ADRM: AddressMode> Future
+    for TransferFuture<'a, 'b, 'c, I2C, ADRM>
+{
+    type Output = Result<(), Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        // Register before advancing, so an interrupt in between is not missed
+        cortex_m::interrupt::free(|cs| {
+            WAKERS[I2C::INDEX]
+                .borrow(cs)
+                .replace(Some(cx.waker().clone()));
+        });
+
+        this.machine.step(&this.i2c.i2c);
+        match this.machine.state {
+            State::Running => {
+                // The handler masked the interrupts before waking us
+                this.machine.listen(&this.i2c.i2c);
+                Poll::Pending
+            }
+            State::Done(result) => Poll::Ready(result),
+        }
+    }
+}
+
+impl<'a, 'b, 'c, I2C: Instance, ADRM: AddressMode> Drop for TransferFuture<'a, 'b, 'c, I2C, ADRM> {
+    fn drop(&mut self) {
+        self.machine.abort(&self.i2c.i2c);
+        cortex_m::interrupt::free(|cs| {
+            WAKERS[I2C::INDEX].borrow(cs).replace(None);
+        });
+    }
+}
+
+impl<I2C, ADRM> embedded_hal_async::i2c::I2c<ADRM> for I2c<I2C, ADRM>
+where
+    I2C: Instance,
+    ADRM: AddressMode + Into<u16>,
+{
+    async fn transaction(
+        &mut self,
+        address: ADRM,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let machine = Machine::start(&self.i2c, address.into(), operations, true);
+        // NOTE(unsafe) the handler only masks the interrupts of this
+        // peripheral and wakes the task
+        unsafe { NVIC::unmask(I2C::INTERRUPT) };
+        TransferFuture { i2c: self, machine }.await
+    }
+}
+
+/// Handle the interrupt of the I2C peripheral `I2C`, this has to be called
+/// from its interrupt handler unless the `i2c-handlers` feature is on.
+///
+/// Masks the interrupts, so they do not fire again before the transaction
+/// advanced, and wakes the waiting task.
+pub fn on_interrupt<I2C: Instance>() {
+    cortex_m::interrupt::free(|cs| {
+        // NOTE(unsafe) only the interrupt enables are touched, the task
+        // re-enables them once it handled the event
+        unlisten_transfer(unsafe { &*I2C::PTR });
+
+        if let Some(waker) = WAKERS[I2C::INDEX].borrow(cs).take() {
+            waker.wake();
+        }
+    });
+}
+
+#[cfg(feature = "i2c-handlers")]
+macro_rules! i2c_handlers {
+    ($($I2CX:ident,)+) => {
+        $(
+            #[interrupt]
+            fn $I2CX() {
+                on_interrupt::<$I2CX>();
+            }
+        )+
+    }
+}
+
+#[cfg(feature = "i2c-handlers")]
+i2c_handlers!(
+    I2C0,
+    I2C1,
+);
@@ -0,0 +1,908 @@
+//! Inter Integrated Circuit implementation
+use crate::ckcu::{Clocks, Pcer};
+use crate::gpio::{AfNumber, Afio, OpenDrain, Output, Pin};
+use crate::hal::{self, delay::DelayNs, digital::InputPin, digital::OutputPin, i2c::Operation};
+use crate::pac::{I2C0, I2C1};
+use crate::Sealed;
+use crate::time::{Hertz, MicroSeconds, RateExtU32};
+
+use core::marker::PhantomData;
+use core::ops::Deref;
+
+pub use crate::hal::i2c::{AddressMode, SevenBitAddress, TenBitAddress};
+
+mod slave;
+pub use slave::{I2cSlave, OwnAddress, OwnAddressError, SlaveEvent};
+
+#[cfg(feature = "async")]
+pub mod asynch;
+#[cfg(feature = "async")]
+pub use asynch::Instance;
+
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// Arbitration error
+    Arbitration,
+    /// Bus error
+    Bus,
+    /// The slave didn't send ACK
+    NotAcknowledge(hal::i2c::NoAcknowledgeSource),
+    /// The bus did not make progress within the configured timeout
+    Timeout,
+}
+
+#[derive(Debug)]
+pub enum Event {
+    RxBufferFull,
+    DataRegisterEmtpyTransmitter,
+    DataRegisterEmptyReceiver,
+    BusError,
+    ReceivedNotAcknowledge,
+    ArbitrationLoss,
+    StopConditionDetected,
+    StartConditionTransmit,
+}
+
+pub trait PinScl<I2C> {}
+
+pub trait PinSda<I2C> {}
+
+#[derive(Debug)]
+pub struct I2c<I2C, ADRM: AddressMode> {
+    i2c: I2C,
+    addressing_mode: PhantomData<ADRM>,
+    /// Status polls before a busy wait times out, on devices without a
+    /// hardware timeout counter
+    budget: Option<u32>,
+}
+
+pub trait I2cExt<I2C>: Sealed {
+    fn i2c<SCL, SDA, F>(
+        self,
+        scl: SCL,
+        sda: SDA,
+        freq: F,
+        clocks: &Clocks,
+    ) -> I2c<I2C, SevenBitAddress>
+    where
+        SCL: PinScl<I2C>,
+        SDA: PinSda<I2C>,
+        F: Into<Hertz>;
+
+    fn i2c_unchecked<F>(self, freq: F, clocks: &Clocks) -> I2c<I2C, SevenBitAddress>
+    where
+        F: Into<Hertz>;
+
+    fn i2c_slave<SCL, SDA>(
+        self,
+        scl: SCL,
+        sda: SDA,
+        address: OwnAddress,
+    ) -> Result<I2cSlave<I2C>, OwnAddressError>
+    where
+        SCL: PinScl<I2C>,
+        SDA: PinSda<I2C>;
+}
+
+impl Sealed for I2C0 {}
+
+impl Sealed for I2C1 {}
+
+macro_rules! i2c {
+    ($($I2CX:ident,)+) => {
+        $(
+            impl I2c<$I2CX, SevenBitAddress>
+            {
+                /// Creates a new I2C peripheral
+                pub fn new<F>(i2c: $I2CX, freq: F, clocks: &Clocks) -> Self
+                where
+                    F: Into<Hertz>,
+                {
+                    let freq = freq.into();
+
+                    assert!(freq <= 1u32.MHz::<1, 1>());
+
+                    // SCL_low = 1/pclk * (SLPG + d)
+                    // SCL_high = 1/pclk * (SHPG + d)
+                    // T_SCL = SCL_low + SCL_high
+                    //
+                    // For HT32F1yyy PCLK = HCLK
+                    // The value of the `d` term depends on the device
+                    #[cfg(any(feature = "ht32f1755", feature = "ht32f1765"))]
+                    let (d_l, d_h) = (9, 7);
+
+                    #[cfg(any(
+                        feature = "ht32f1653",
+                        feature = "ht32f1654",
+                        feature = "ht32f1655",
+                        feature = "ht32f1656",
+                    ))]
+                    let (d_l, d_h) = (6, 6); // Depends on SEQ_FILTER, which we leave on 0b00
+
+                    #[cfg(any(
+                        feature = "ht32f1251",
+                        feature = "ht32f1252",
+                        feature = "ht32f1253",
+                    ))]
+                    let (d_l, d_h) = (7, 7);
+
+                    let (shpg, slpg) = if freq > 100u32.kHz::<1, 1>() {
+                        // We are in Fast-mode or Fast-mode Plus, this means
+                        // SCL_low = 2 * SCL_high, refer to I2C spec page 48
+                        // -> SCL_low = 2/3 SCL
+                        // -> SLPG = (2 * PCLK) / (3 * SCL) - d_l
+                        let slpg = ((2 * clocks.pclk().raw()) / (3 * freq.raw())) - d_l;
+
+                        // 1/pclk * (SLPG + d_l) = 2/pclk * (SHPG + d_h)
+                        // -> SHPG = (SLPG + d_l)/2 - d_h
+                        // + 1 serves as a correction factor so SCL gets slower
+                        // rather than larger as freq
+                        let shpg = ((slpg + d_l) / 2) - d_h + 1;
+                        (shpg, slpg)
+                    } else {
+                        // We are in Standard mode, this means
+                        // SCL_low = SCL_high, refer to I2C spec page 48
+                        // -> SLPG = SHPG = pclk / (2*SCL) - d
+                        let scl_div = ((clocks.pclk().raw()) / (2 * freq.raw()));
+                        (scl_div - d_h, scl_div - d_l)
+                    };
+
+                    // reset the I2C port before using it
+                    i2c.reset();
+                    // enable the AHB clock for the I2C port
+                    i2c.enable();
+
+                    // Configure the SCL clock values
+                    i2c.i2c_shpgr
+                        .modify(|_, w| unsafe { w.shpg().bits(shpg.try_into().unwrap()) });
+                    i2c.i2c_slpgr
+                        .modify(|_, w| unsafe { w.slpg().bits(slpg.try_into().unwrap()) });
+                    // Enable the I2C port
+                    i2c.i2c_cr.modify(|_, w| w.i2cen().set_bit());
+                    I2c { i2c, addressing_mode: PhantomData, budget: None }
+                }
+            }
+
+            impl<ADRM: AddressMode> I2c<$I2CX, ADRM>
+            {
+                /// Release a slave that holds SDA low, by clocking SCL until it
+                /// lets go and sending a STOP
+                ///
+                /// The I2C does not keep its pins, so they have to be passed as
+                /// erased pins, e.g. by creating the I2C with `i2c_unchecked`
+                /// and keeping the downgraded pins. The pins are switched to
+                /// GPIO for the recovery and back to `af` afterwards, then the
+                /// peripheral is reset and its configuration restored.
+                ///
+                /// Returns [`Error::Bus`] if SDA is still held low after nine
+                /// clock pulses.
+                pub fn recover_bus<D: DelayNs>(
+                    &mut self,
+                    scl: &mut Pin<Output<OpenDrain>>,
+                    sda: &mut Pin<Output<OpenDrain>>,
+                    af: AfNumber,
+                    afio: &mut Afio,
+                    delay: &mut D,
+                ) -> Result<(), Error> {
+                    // Half a period of a 100 kHz clock
+                    const HALF_PERIOD_US: u32 = 5;
+
+                    // Save the configuration, the reset clears it
+                    let cr = self.i2c.i2c_cr.read();
+                    let ten_bit = cr.adrm().bit_is_set();
+                    #[cfg(not(any(
+                        feature = "ht32f1251",
+                        feature = "ht32f1252",
+                        feature = "ht32f1253",
+                    )))]
+                    let entout = cr.entout().bit_is_set();
+                    let ier = self.i2c.i2c_ier.read().bits();
+                    let shpgr = self.i2c.i2c_shpgr.read().bits();
+                    let slpgr = self.i2c.i2c_slpgr.read().bits();
+                    #[cfg(not(any(
+                        feature = "ht32f1251",
+                        feature = "ht32f1252",
+                        feature = "ht32f1253",
+                    )))]
+                    let tout = self.i2c.i2c_tout.read().bits();
+
+                    self.i2c.i2c_cr.modify(|_, w| w.i2cen().clear_bit());
+
+                    // Release both lines before handing them to the GPIO
+                    scl.set_high().ok();
+                    sda.set_high().ok();
+                    scl.set_alternate(afio, AfNumber::DEFAULT);
+                    sda.set_alternate(afio, AfNumber::DEFAULT);
+                    // SDA is sampled through DINR, which only follows the pin
+                    // while its input buffer is enabled
+                    let sda_input = sda.set_input_enable(true);
+                    delay.delay_us(HALF_PERIOD_US);
+
+                    for _ in 0..9 {
+                        if sda.is_high() == Ok(true) {
+                            break;
+                        }
+                        scl.set_low().ok();
+                        delay.delay_us(HALF_PERIOD_US);
+                        scl.set_high().ok();
+                        delay.delay_us(HALF_PERIOD_US);
+                    }
+
+                    // STOP: SDA rises while SCL is high
+                    scl.set_low().ok();
+                    sda.set_low().ok();
+                    delay.delay_us(HALF_PERIOD_US);
+                    scl.set_high().ok();
+                    delay.delay_us(HALF_PERIOD_US);
+                    sda.set_high().ok();
+                    delay.delay_us(HALF_PERIOD_US);
+                    let released = sda.is_high() == Ok(true);
+
+                    sda.set_input_enable(sda_input);
+                    scl.set_alternate(afio, af);
+                    sda.set_alternate(afio, af);
+
+                    // Re-initialise the peripheral
+                    self.i2c.reset();
+                    self.i2c.i2c_shpgr.write(|w| unsafe { w.bits(shpgr) });
+                    self.i2c.i2c_slpgr.write(|w| unsafe { w.bits(slpgr) });
+                    #[cfg(not(any(
+                        feature = "ht32f1251",
+                        feature = "ht32f1252",
+                        feature = "ht32f1253",
+                    )))]
+                    {
+                        self.i2c.i2c_tout.write(|w| unsafe { w.bits(tout) });
+                        self.i2c.i2c_cr.modify(|_, w| w.entout().bit(entout));
+                    }
+                    self.i2c.i2c_ier.write(|w| unsafe { w.bits(ier) });
+                    self.i2c.i2c_cr.modify(|_, w| w.adrm().bit(ten_bit).i2cen().set_bit());
+
+                    if released {
+                        Ok(())
+                    } else {
+                        Err(Error::Bus)
+                    }
+                }
+            }
+
+            impl I2cExt<$I2CX> for $I2CX
+            {
+                fn i2c<SCL, SDA, F>(
+                    self,
+                    _scl: SCL,
+                    _sda: SDA,
+                    freq: F,
+                    clocks: &Clocks
+                ) -> I2c<$I2CX, SevenBitAddress>
+                where
+                    SCL: PinScl<$I2CX>,
+                    SDA: PinSda<$I2CX>,
+                    F: Into<Hertz>,
+                {
+                    I2c::<$I2CX, SevenBitAddress>::new(self, freq, clocks)
+                }
+
+                fn i2c_unchecked<F>(self, freq: F, clocks: &Clocks) -> I2c<$I2CX, SevenBitAddress>
+                where
+                    F: Into<Hertz>,
+                {
+                    I2c::<$I2CX, SevenBitAddress>::new(self, freq, clocks)
+                }
+
+                fn i2c_slave<SCL, SDA>(
+                    self,
+                    _scl: SCL,
+                    _sda: SDA,
+                    address: OwnAddress
+                ) -> Result<I2cSlave<$I2CX>, OwnAddressError>
+                where
+                    SCL: PinScl<$I2CX>,
+                    SDA: PinSda<$I2CX>,
+                {
+                    I2cSlave::<$I2CX>::new(self, address)
+                }
+            }
+        )+
+    }
+}
+
+i2c!(
+    I2C0,
+    I2C1,
+);
+
+/// Check the flag of the hardware timeout counter
+#[cfg(not(any(feature = "ht32f1251", feature = "ht32f1252", feature = "ht32f1253")))]
+fn has_timed_out(status: &crate::pac::i2c0::i2c_sr::R) -> bool {
+    status.toutf().bit_is_set()
+}
+
+/// The HT32F125x has no hardware timeout counter
+#[cfg(any(feature = "ht32f1251", feature = "ht32f1252", feature = "ht32f1253"))]
+fn has_timed_out(_status: &crate::pac::i2c0::i2c_sr::R) -> bool {
+    false
+}
+
+impl<I2C, ADRM> I2c<I2C, ADRM>
+where
+    I2C: Deref<Target = crate::pac::i2c0::RegisterBlock>,
+    ADRM: AddressMode,
+{
+    pub fn free(self) -> I2C {
+        self.i2c
+    }
+
+    /// Fail with [`Error::Timeout`] if the bus does not make progress
+    /// within `timeout`
+    ///
+    /// The hardware timeout counter is used where available, it runs from
+    /// PCLK with a prescaler of up to 128, longer timeouts are clamped. The
+    /// HT32F125x instead counts status polls, so the timeout is at least
+    /// `timeout` but may be several times longer.
+    ///
+    /// A `timeout` of 0 waits for the bus forever, like
+    /// [`disable_timeout`](I2c::disable_timeout).
+    pub fn set_timeout(&mut self, timeout: MicroSeconds, clocks: &Clocks) {
+        if timeout.ticks() == 0 {
+            return self.disable_timeout();
+        }
+
+        let cycles = timeout.ticks() as u64 * clocks.pclk().raw() as u64 / 1_000_000;
+        self.set_timeout_cycles(cycles);
+    }
+
+    /// Time out after `cycles` PCLK cycles
+    fn set_timeout_cycles(&mut self, cycles: u64) {
+        #[cfg(not(any(feature = "ht32f1251", feature = "ht32f1252", feature = "ht32f1253")))]
+        {
+            // Pick the smallest prescaler 2^psc that fits the 16 bit counter
+            let mut psc = 0;
+            while cycles >> psc > 0xFFFF && psc < 7 {
+                psc += 1;
+            }
+            let tout = (cycles >> psc).clamp(1, 0xFFFF) as u16;
+
+            // Stop the counter while it is reloaded
+            self.i2c.i2c_cr.modify(|_, w| w.entout().clear_bit());
+            #[rustfmt::skip]
+            self.i2c.i2c_tout.write(|w| unsafe {
+                w.psc().bits(psc as u8)
+                 .tout().bits(tout)
+            });
+            self.i2c.i2c_cr.modify(|_, w| w.entout().set_bit());
+        }
+
+        #[cfg(any(feature = "ht32f1251", feature = "ht32f1252", feature = "ht32f1253"))]
+        {
+            self.budget = Some(cycles.clamp(1, u32::MAX as u64) as u32);
+        }
+    }
+
+    /// Wait for the bus forever
+    pub fn disable_timeout(&mut self) {
+        #[cfg(not(any(feature = "ht32f1251", feature = "ht32f1252", feature = "ht32f1253")))]
+        {
+            self.i2c.i2c_cr.modify(|_, w| w.entout().clear_bit());
+            self.i2c.i2c_tout.write(|w| unsafe { w.tout().bits(0) });
+        }
+
+        self.budget = None;
+    }
+
+    pub fn listen(&mut self, event: Event) {
+        match event {
+            Event::RxBufferFull => self.i2c.i2c_ier.modify(|_, w| w.rxbfie().set_bit()),
+            Event::DataRegisterEmtpyTransmitter => {
+                self.i2c.i2c_ier.modify(|_, w| w.txdeie().set_bit())
+            }
+            Event::DataRegisterEmptyReceiver => {
+                self.i2c.i2c_ier.modify(|_, w| w.rxdneie().set_bit())
+            }
+            Event::BusError => self.i2c.i2c_ier.modify(|_, w| w.buserrie().set_bit()),
+            Event::ReceivedNotAcknowledge => self.i2c.i2c_ier.modify(|_, w| w.rxnackie().set_bit()),
+            Event::ArbitrationLoss => self.i2c.i2c_ier.modify(|_, w| w.arblosie().set_bit()),
+            Event::StopConditionDetected => self.i2c.i2c_ier.modify(|_, w| w.stoie().set_bit()),
+            Event::StartConditionTransmit => self.i2c.i2c_ier.modify(|_, w| w.staie().set_bit()),
+        }
+    }
+
+    pub fn unlisten(&mut self, event: Event) {
+        match event {
+            Event::RxBufferFull => self.i2c.i2c_ier.modify(|_, w| w.rxbfie().clear_bit()),
+            Event::DataRegisterEmtpyTransmitter => {
+                self.i2c.i2c_ier.modify(|_, w| w.txdeie().clear_bit())
+            }
+            Event::DataRegisterEmptyReceiver => {
+                self.i2c.i2c_ier.modify(|_, w| w.rxdneie().clear_bit())
+            }
+            Event::BusError => self.i2c.i2c_ier.modify(|_, w| w.buserrie().clear_bit()),
+            Event::ReceivedNotAcknowledge => {
+                self.i2c.i2c_ier.modify(|_, w| w.rxnackie().clear_bit())
+            }
+            Event::ArbitrationLoss => self.i2c.i2c_ier.modify(|_, w| w.arblosie().clear_bit()),
+            Event::StopConditionDetected => self.i2c.i2c_ier.modify(|_, w| w.stoie().clear_bit()),
+            Event::StartConditionTransmit => self.i2c.i2c_ier.modify(|_, w| w.staie().clear_bit()),
+        }
+    }
+}
+
+impl<I2C> I2c<I2C, SevenBitAddress>
+where
+    I2C: Deref<Target = crate::pac::i2c0::RegisterBlock>,
+{
+    /// Change the addressing to 10-bit mode
+    pub fn set_10bit_address(self) -> I2c<I2C, TenBitAddress> {
+        self.i2c.i2c_cr.modify(|_, w| w.adrm().set_bit());
+        I2c {
+            i2c: self.i2c,
+            addressing_mode: PhantomData,
+            budget: self.budget,
+        }
+    }
+}
+
+impl<I2C> I2c<I2C, TenBitAddress>
+where
+    I2C: Deref<Target = crate::pac::i2c0::RegisterBlock>,
+{
+    /// Change the addressing to 7-bit mode
+    pub fn set_7bit_address(self) -> I2c<I2C, SevenBitAddress> {
+        self.i2c.i2c_cr.modify(|_, w| w.adrm().clear_bit());
+        I2c {
+            i2c: self.i2c,
+            addressing_mode: PhantomData,
+            budget: self.budget,
+        }
+    }
+}
+
+impl<I2C, ADRM> I2c<I2C, ADRM>
+where
+    I2C: Deref<Target = crate::pac::i2c0::RegisterBlock>,
+    ADRM: AddressMode + Into<u16>,
+{
+    /// Start an interrupt driven transaction
+    ///
+    /// The operations are executed like in
+    /// [`transaction`](hal::i2c::I2c::transaction), but instead of busy
+    /// waiting the returned [`Transfer`] is advanced by calling
+    /// [`Transfer::on_interrupt`] from the I2C interrupt handler, or
+    /// [`Transfer::poll`] from the main loop.
+    pub fn transfer<'a, 'b>(
+        self,
+        address: ADRM,
+        operations: &'a mut [Operation<'b>],
+    ) -> Transfer<'a, 'b, I2C, ADRM> {
+        let machine = Machine::start(&self.i2c, address.into(), operations, true);
+        Transfer { i2c: self, machine }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Running,
+    Done(Result<(), Error>),
+}
+
+/// Disable the interrupts used while a transaction is in progress
+fn unlisten_transfer(i2c: &crate::pac::i2c0::RegisterBlock) {
+    #[rustfmt::skip]
+    i2c.i2c_ier.modify(|_, w| {
+        w.adrsie().clear_bit()
+         .rxdneie().clear_bit()
+         .txdeie().clear_bit()
+         .rxnackie().clear_bit()
+         .arblosie().clear_bit()
+         .buserrie().clear_bit()
+    });
+
+    #[cfg(not(any(feature = "ht32f1251", feature = "ht32f1252", feature = "ht32f1253")))]
+    i2c.i2c_ier.modify(|_, w| w.toutie().clear_bit());
+}
+
+/// The state of a transaction, shared by the blocking, the interrupt
+/// driven and the async transfers
+struct Machine<'a, 'b> {
+    address: u16,
+    operations: &'a mut [Operation<'b>],
+    /// Index of the current operation
+    op: usize,
+    /// Index of the next byte in the current operation
+    pos: usize,
+    /// Whether the slave acknowledged the last address frame
+    addressed: bool,
+    /// Whether the transaction is driven by the I2C interrupt
+    interrupts: bool,
+    state: State,
+}
+
+impl<'a, 'b> Machine<'a, 'b> {
+    fn start(
+        i2c: &crate::pac::i2c0::RegisterBlock,
+        address: u16,
+        operations: &'a mut [Operation<'b>],
+        interrupts: bool,
+    ) -> Self {
+        let mut machine = Machine {
+            address,
+            operations,
+            op: 0,
+            pos: 0,
+            addressed: false,
+            interrupts,
+            state: State::Running,
+        };
+
+        if machine.operations.is_empty() {
+            machine.state = State::Done(Ok(()));
+        } else {
+            machine.begin(i2c);
+        }
+        machine
+    }
+
+    fn is_done(&self) -> bool {
+        matches!(self.state, State::Done(_))
+    }
+
+    /// The position in the transaction, changes whenever it advanced
+    fn progress(&self) -> (usize, usize, bool) {
+        (self.op, self.pos, self.addressed)
+    }
+
+    /// Whether the current operation is the last read before the
+    /// direction changes, so its last byte has to be NACKed
+    fn is_final_read(&self) -> bool {
+        !matches!(self.operations.get(self.op + 1), Some(Operation::Read(_)))
+    }
+
+    /// Enable the interrupts needed by the current phase of the transaction
+    fn listen(&self, i2c: &crate::pac::i2c0::RegisterBlock) {
+        let rwd = matches!(self.operations[self.op], Operation::Read(_));
+
+        #[rustfmt::skip]
+        i2c.i2c_ier.modify(|_, w| {
+            w.adrsie().bit(!self.addressed)
+             .rxdneie().bit(self.addressed && rwd)
+             .txdeie().bit(self.addressed && !rwd)
+             .rxnackie().set_bit()
+             .arblosie().set_bit()
+             .buserrie().set_bit()
+        });
+
+        #[cfg(not(any(feature = "ht32f1251", feature = "ht32f1252", feature = "ht32f1253")))]
+        i2c.i2c_ier.modify(|_, w| w.toutie().set_bit());
+    }
+
+    /// Send the (repeated) START with the address frame for the current
+    /// operation
+    fn begin(&mut self, i2c: &crate::pac::i2c0::RegisterBlock) {
+        let (rwd, len) = match &self.operations[self.op] {
+            Operation::Read(buffer) => (true, buffer.len()),
+            Operation::Write(buffer) => (false, buffer.len()),
+        };
+
+        if rwd {
+            // NACK right away if at most one byte is going to be received
+            let aa = !(self.is_final_read() && len <= 1);
+            i2c.i2c_cr.modify(|_, w| w.aa().bit(aa));
+        }
+
+        self.addressed = false;
+        if self.interrupts {
+            self.listen(i2c);
+        }
+
+        #[rustfmt::skip]
+        i2c.i2c_tar.modify(|_, w| unsafe {
+            // Set direction
+            w.rwd().bit(rwd)
+            // Set slave address
+             .tar().bits(self.address)
+        });
+    }
+
+    /// Move on to the next operation, a change of direction requires a
+    /// repeated START
+    fn next(&mut self, i2c: &crate::pac::i2c0::RegisterBlock) {
+        let was_read = matches!(self.operations[self.op], Operation::Read(_));
+        self.op += 1;
+        self.pos = 0;
+
+        match self.operations.get(self.op) {
+            None => self.finish(i2c, Ok(())),
+            Some(Operation::Read(buffer)) if was_read => {
+                if self.is_final_read() && buffer.len() <= 1 {
+                    i2c.i2c_cr.modify(|_, w| w.aa().clear_bit());
+                }
+            }
+            Some(Operation::Write(_)) if !was_read => {}
+            Some(_) => self.begin(i2c),
+        }
+    }
+
+    /// Leave the bus idle, whatever the result: disable the interrupts,
+    /// clear the error flags and the received data and send the STOP
+    fn finish(&mut self, i2c: &crate::pac::i2c0::RegisterBlock, result: Result<(), Error>) {
+        unlisten_transfer(i2c);
+
+        // write 1 to clear
+        #[rustfmt::skip]
+        i2c.i2c_sr.write(|w| {
+            w.arblos().set_bit()
+             .rxnack().set_bit()
+             .buserr().set_bit()
+        });
+        #[cfg(not(any(feature = "ht32f1251", feature = "ht32f1252", feature = "ht32f1253")))]
+        i2c.i2c_sr.write(|w| w.toutf().set_bit());
+
+        // A byte received after the NACK would block the next read
+        if i2c.i2c_sr.read().rxdne().bit_is_set() {
+            i2c.i2c_dr.read();
+        }
+
+        // After losing arbitration the bus belongs to another master
+        if result != Err(Error::Arbitration) {
+            i2c.i2c_cr.modify(|_, w| w.stop().set_bit());
+        }
+
+        self.state = State::Done(result);
+    }
+
+    /// Abort a transaction that did not complete yet
+    fn abort(&mut self, i2c: &crate::pac::i2c0::RegisterBlock) {
+        if !self.is_done() {
+            self.finish(i2c, Ok(()));
+        }
+    }
+
+    /// Advance the transaction as far as the status flags allow
+    fn step(&mut self, i2c: &crate::pac::i2c0::RegisterBlock) {
+        while self.state == State::Running {
+            let status = i2c.i2c_sr.read();
+
+            if status.arblos().bit_is_set() {
+                return self.finish(i2c, Err(Error::Arbitration));
+            } else if status.rxnack().bit_is_set() {
+                let source = if self.addressed {
+                    hal::i2c::NoAcknowledgeSource::Data
+                } else {
+                    hal::i2c::NoAcknowledgeSource::Address
+                };
+                return self.finish(i2c, Err(Error::NotAcknowledge(source)));
+            } else if status.buserr().bit_is_set() {
+                return self.finish(i2c, Err(Error::Bus));
+            } else if has_timed_out(&status) {
+                return self.finish(i2c, Err(Error::Timeout));
+            }
+
+            if !self.addressed {
+                // Wait for the address frame to be sent and ACKed
+                if status.adrs().bit_is_clear() {
+                    return;
+                }
+                self.addressed = true;
+                if self.interrupts {
+                    self.listen(i2c);
+                }
+            }
+
+            let final_read = self.is_final_read();
+            match &mut self.operations[self.op] {
+                Operation::Write(buffer) => {
+                    if !buffer.is_empty() && status.txde().bit_is_clear() {
+                        return;
+                    }
+
+                    if let Some(byte) = buffer.get(self.pos) {
+                        i2c.i2c_dr.write(|w| unsafe { w.data().bits(*byte) });
+                        self.pos += 1;
+                        // Wait for the last byte to be sent as well
+                        return;
+                    }
+                }
+                Operation::Read(buffer) => {
+                    if !buffer.is_empty() {
+                        if status.rxdne().bit_is_clear() {
+                            return;
+                        }
+
+                        buffer[self.pos] = i2c.i2c_dr.read().data().bits();
+                        self.pos += 1;
+
+                        if final_read && self.pos + 1 == buffer.len() {
+                            // send a NACK for the last byte
+                            i2c.i2c_cr.modify(|_, w| w.aa().clear_bit());
+                        }
+                        if self.pos < buffer.len() {
+                            return;
+                        }
+                    }
+                }
+            }
+
+            // The current operation is complete
+            self.next(i2c);
+        }
+    }
+}
+
+/// An interrupt driven I2C transaction in progress
+///
+/// The transfer only advances when [`on_interrupt`](Transfer::on_interrupt)
+/// or [`poll`](Transfer::poll) is called, the I2C interrupt has to be
+/// unmasked in the NVIC to drive it from the interrupt handler.
+pub struct Transfer<'a, 'b, I2C, ADRM: AddressMode> {
+    i2c: I2c<I2C, ADRM>,
+    machine: Machine<'a, 'b>,
+}
+
+impl<'a, 'b, I2C, ADRM> Transfer<'a, 'b, I2C, ADRM>
+where
+    I2C: Deref<Target = crate::pac::i2c0::RegisterBlock>,
+    ADRM: AddressMode,
+{
+    /// Advance the transfer, call this from the I2C interrupt handler
+    pub fn on_interrupt(&mut self) {
+        self.machine.step(&self.i2c.i2c);
+    }
+
+    /// Advance the transfer and return its result once it completed
+    pub fn poll(&mut self) -> nb::Result<(), Error> {
+        self.machine.step(&self.i2c.i2c);
+
+        match self.machine.state {
+            State::Running => Err(nb::Error::WouldBlock),
+            State::Done(result) => result.map_err(nb::Error::Other),
+        }
+    }
+
+    /// Check whether the transfer completed, successfully or not
+    pub fn is_done(&self) -> bool {
+        self.machine.is_done()
+    }
+
+    /// Release the I2C peripheral and the operations
+    ///
+    /// A transfer that did not complete yet is aborted with a STOP.
+    pub fn free(mut self) -> (I2c<I2C, ADRM>, &'a mut [Operation<'b>]) {
+        self.machine.abort(&self.i2c.i2c);
+        (self.i2c, self.machine.operations)
+    }
+}
+
+impl hal::i2c::Error for Error {
+    fn kind(&self) -> hal::i2c::ErrorKind {
+        match *self {
+            Error::Arbitration => hal::i2c::ErrorKind::ArbitrationLoss,
+            Error::Bus => hal::i2c::ErrorKind::Bus,
+            Error::NotAcknowledge(source) => hal::i2c::ErrorKind::NoAcknowledge(source),
+            Error::Timeout => hal::i2c::ErrorKind::Other,
+        }
+    }
+}
+
+impl<I2C, ADRM: AddressMode> hal::i2c::ErrorType for I2c<I2C, ADRM> {
+    type Error = Error;
+}
+
+impl<I2C, ADRM> hal::i2c::I2c<ADRM> for I2c<I2C, ADRM>
+where
+    I2C: Deref<Target = crate::pac::i2c0::RegisterBlock>,
+    ADRM: AddressMode + Into<u16>,
+{
+    fn transaction(
+        &mut self,
+        address: ADRM,
+        operations: &mut [hal::i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let mut machine = Machine::start(&self.i2c, address.into(), operations, false);
+        let mut budget = self.budget;
+
+        while !machine.is_done() {
+            let progress = machine.progress();
+            machine.step(&self.i2c);
+
+            if machine.progress() != progress {
+                budget = self.budget;
+            } else if let Some(polls) = budget.as_mut() {
+                if *polls == 0 {
+                    machine.finish(&self.i2c, Err(Error::Timeout));
+                } else {
+                    *polls -= 1;
+                }
+            }
+        }
+
+        let result = match machine.state {
+            State::Done(result) => result,
+            State::Running => unreachable!(),
+        };
+
+        // wait for the STOP to be sent, unless another master owns the bus
+        if result != Err(Error::Arbitration) {
+            let mut budget = self.budget;
+            loop {
+                let status = self.i2c.i2c_sr.read();
+                if status.busbusy().bit_is_clear() || has_timed_out(&status) {
+                    break;
+                }
+                if let Some(polls) = budget.as_mut() {
+                    if *polls == 0 {
+                        break;
+                    }
+                    *polls -= 1;
+                }
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::i2c::I2c as _;
+    use crate::pac::i2c0::RegisterBlock;
+
+    use core::cell::UnsafeCell;
+
+    /// Plain memory standing in for the I2C registers, nothing drives the
+    /// bus so every transaction stalls before its address is ACKed
+    struct Regs(UnsafeCell<[u32; core::mem::size_of::<RegisterBlock>() / 4]>);
+
+    impl Regs {
+        fn new() -> Self {
+            Regs(UnsafeCell::new([0; core::mem::size_of::<RegisterBlock>() / 4]))
+        }
+    }
+
+    impl Deref for Regs {
+        type Target = RegisterBlock;
+
+        fn deref(&self) -> &RegisterBlock {
+            unsafe { &*(self.0.get() as *const RegisterBlock) }
+        }
+    }
+
+    fn i2c() -> I2c<Regs, SevenBitAddress> {
+        I2c {
+            i2c: Regs::new(),
+            addressing_mode: PhantomData,
+            budget: None,
+        }
+    }
+
+    #[cfg(not(any(feature = "ht32f1251", feature = "ht32f1252", feature = "ht32f1253")))]
+    #[test]
+    fn timeout_enables_the_counter() {
+        let mut i2c = i2c();
+
+        // 1 ms at 72 MHz needs a prescaler of 2
+        i2c.set_timeout_cycles(72_000);
+        assert!(i2c.i2c.i2c_cr.read().entout().bit_is_set());
+        assert_eq!(i2c.i2c.i2c_tout.read().psc().bits(), 1);
+        assert_eq!(i2c.i2c.i2c_tout.read().tout().bits(), 36_000);
+
+        i2c.disable_timeout();
+        assert!(i2c.i2c.i2c_cr.read().entout().bit_is_clear());
+        assert_eq!(i2c.i2c.i2c_tout.read().tout().bits(), 0);
+    }
+
+    #[test]
+    fn stuck_bus_times_out() {
+        let mut i2c = i2c();
+        i2c.set_timeout_cycles(72_000);
+
+        // The counter expired while waiting for the address to be ACKed
+        #[cfg(not(any(feature = "ht32f1251", feature = "ht32f1252", feature = "ht32f1253")))]
+        i2c.i2c.i2c_sr.write(|w| w.toutf().set_bit());
+
+        assert_eq!(i2c.write(0x50, &[0x00]), Err(Error::Timeout));
+        // The bus is released with a STOP
+        assert!(i2c.i2c.i2c_cr.read().stop().bit_is_set());
+    }
+}
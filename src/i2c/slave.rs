@@ -0,0 +1,219 @@
+//! I2C slave implementation
+use crate::ckcu::Pcer;
+use crate::pac::{I2C0, I2C1};
+
+use core::ops::Deref;
+
+use super::Error;
+
+/// The own address of the slave
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OwnAddress {
+    /// A 7-bit address, up to 0x7F
+    SevenBit(u8),
+    /// A 10-bit address, up to 0x3FF
+    TenBit(u16),
+}
+
+/// The own address of the slave is rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OwnAddressError {
+    /// The address does not fit its addressing mode
+    OutOfRange,
+}
+
+/// Events reported to the slave, in the order they occur in a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlaveEvent {
+    /// The master addressed us to read, answer the following
+    /// [`ByteRequested`](SlaveEvent::ByteRequested) events
+    AddressedAsRead,
+    /// The master addressed us to write
+    AddressedAsWrite,
+    /// The master addressed all slaves with the general call address
+    GeneralCall,
+    /// The master wrote a byte
+    ByteReceived(u8),
+    /// The master wants to read a byte, reply with
+    /// [`write`](I2cSlave::write)
+    ByteRequested,
+    /// The master did not acknowledge the last byte it read
+    NotAcknowledge,
+    /// The master ended the transaction
+    Stop,
+}
+
+/// Status flags that are cleared by reading I2C_SR, and therefore have to
+/// be remembered until they are reported
+const STO: u8 = 1 << 0;
+const ADRS: u8 = 1 << 1;
+const GCS: u8 = 1 << 2;
+
+#[derive(Debug)]
+pub struct I2cSlave<I2C> {
+    i2c: I2C,
+    /// Events seen by an earlier status read, not reported yet
+    pending: u8,
+    /// Whether the pending ADRS is a read request
+    read_requested: bool,
+}
+
+macro_rules! i2c_slave {
+    ($($I2CX:ident,)+) => {
+        $(
+            impl I2cSlave<$I2CX> {
+                /// Creates a new I2C slave, answering on `address`
+                ///
+                /// Returns [`OwnAddressError::OutOfRange`] if `address` does not
+                /// fit its addressing mode.
+                pub fn new(i2c: $I2CX, address: OwnAddress) -> Result<Self, OwnAddressError> {
+                    let (addr, ten_bit) = match address {
+                        OwnAddress::SevenBit(addr) if addr <= 0x7F => (addr as u16, false),
+                        OwnAddress::TenBit(addr) if addr <= 0x3FF => (addr, true),
+                        _ => return Err(OwnAddressError::OutOfRange),
+                    };
+
+                    // reset the I2C port before using it
+                    i2c.reset();
+                    // enable the AHB clock for the I2C port
+                    i2c.enable();
+
+                    i2c.i2c_addr.write(|w| unsafe { w.addr().bits(addr) });
+
+                    #[rustfmt::skip]
+                    i2c.i2c_cr.modify(|_, w| {
+                        // Set addressing mode
+                        w.adrm().bit(ten_bit)
+                        // ACK our address and the received bytes
+                         .aa().set_bit()
+                        // Enable the I2C port
+                         .i2cen().set_bit()
+                    });
+
+                    Ok(I2cSlave {
+                        i2c,
+                        pending: 0,
+                        read_requested: false,
+                    })
+                }
+            }
+        )+
+    }
+}
+
+i2c_slave!(
+    I2C0,
+    I2C1,
+);
+
+impl<I2C> I2cSlave<I2C>
+where
+    I2C: Deref<Target = crate::pac::i2c0::RegisterBlock>,
+{
+    pub fn free(self) -> I2C {
+        self.i2c.i2c_cr.modify(|_, w| w.i2cen().clear_bit());
+        self.i2c
+    }
+
+    /// Ignore the address bits that are set in `mask`, so the slave
+    /// answers on a range of addresses
+    pub fn set_address_mask(&mut self, mask: u16) {
+        self.i2c
+            .i2c_addmr
+            .write(|w| unsafe { w.addmr().bits(mask) });
+    }
+
+    /// Answer on the general call address 0
+    pub fn enable_general_call(&mut self) {
+        self.i2c.i2c_cr.modify(|_, w| w.gcen().set_bit());
+    }
+
+    pub fn disable_general_call(&mut self) {
+        self.i2c.i2c_cr.modify(|_, w| w.gcen().clear_bit());
+    }
+
+    /// Enable the interrupts for all slave events, the I2C interrupt
+    /// handler then calls [`event`](I2cSlave::event) until it returns
+    /// `WouldBlock`
+    pub fn listen(&mut self) {
+        self.set_interrupts(true);
+    }
+
+    /// Disable the interrupts for all slave events
+    pub fn unlisten(&mut self) {
+        self.set_interrupts(false);
+    }
+
+    fn set_interrupts(&mut self, enable: bool) {
+        #[rustfmt::skip]
+        self.i2c.i2c_ier.modify(|_, w| {
+            w.adrsie().bit(enable)
+             .gcsie().bit(enable)
+             .stoie().bit(enable)
+             .rxdneie().bit(enable)
+             .txdeie().bit(enable)
+             .rxnackie().bit(enable)
+             .buserrie().bit(enable)
+        });
+    }
+
+    /// Returns the next event, or `WouldBlock` if nothing happened
+    ///
+    /// Wait for the next event with `nb::block!(slave.event())`.
+    pub fn event(&mut self) -> nb::Result<SlaveEvent, Error> {
+        let status = self.i2c.i2c_sr.read();
+
+        if status.buserr().bit_is_set() {
+            // write 1 to clear
+            self.i2c.i2c_sr.write(|w| w.buserr().set_bit());
+            return Err(nb::Error::Other(Error::Bus));
+        }
+
+        if status.sto().bit_is_set() {
+            self.pending |= STO;
+        }
+        if status.adrs().bit_is_set() {
+            self.pending |= ADRS;
+            self.read_requested = status.txnrx().bit_is_set();
+        }
+        if status.gcs().bit_is_set() {
+            self.pending |= GCS;
+        }
+
+        // The address and the data of a short transaction can be latched
+        // by the same status read as its stop, report them first
+        if status.rxnack().bit_is_set() {
+            // write 1 to clear
+            self.i2c.i2c_sr.write(|w| w.rxnack().set_bit());
+            Ok(SlaveEvent::NotAcknowledge)
+        } else if self.pending & GCS != 0 {
+            // A general call also matches the address
+            self.pending &= !(GCS | ADRS);
+            Ok(SlaveEvent::GeneralCall)
+        } else if self.pending & ADRS != 0 {
+            self.pending &= !ADRS;
+            if self.read_requested {
+                Ok(SlaveEvent::AddressedAsRead)
+            } else {
+                Ok(SlaveEvent::AddressedAsWrite)
+            }
+        } else if status.rxdne().bit_is_set() {
+            Ok(SlaveEvent::ByteReceived(
+                self.i2c.i2c_dr.read().data().bits(),
+            ))
+        } else if self.pending & STO != 0 {
+            self.pending &= !STO;
+            Ok(SlaveEvent::Stop)
+        } else if status.txde().bit_is_set() && status.txnrx().bit_is_set() {
+            Ok(SlaveEvent::ByteRequested)
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Send `byte` to the master, after
+    /// [`ByteRequested`](SlaveEvent::ByteRequested)
+    pub fn write(&mut self, byte: u8) {
+        self.i2c.i2c_dr.write(|w| unsafe { w.data().bits(byte) });
+    }
+}
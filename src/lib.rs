@@ -48,11 +48,15 @@ pub use ht32f1yyy::ht32f175x as pac;
 pub use crate::pac::interrupt;
 
 pub mod ckcu;
+pub mod delay;
+pub mod dwt;
 pub mod gpio;
 pub mod i2c;
 pub mod spi;
 pub mod time;
 
+mod pins;
+
 #[cfg(not(any(
     feature = "ht32f1251",
     feature = "ht32f1252",
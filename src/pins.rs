@@ -0,0 +1,208 @@
+//! Pin to peripheral mapping tables
+//!
+//! Every alternate function mapping of a device family is listed exactly
+//! once, in the `pin_table!` of that family. Each entry implements the pin
+//! trait of its peripheral signal and is checked against all other entries
+//! at compile time, so no pin can be assigned to two signals on the same AF.
+//!
+//! Refer to the "Alternate Function Mapping" table in the datasheet of the
+//! device when adding entries. The HT32F1653/54/55/56 share a single
+//! datasheet and AF table, so they share one pin table. The HT32F125x has
+//! no pin table yet.
+
+/// A single row of a pin table
+struct Entry {
+    pin: &'static str,
+    af: &'static str,
+    signal: &'static str,
+}
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Fails the build if the same pin and AF is listed for two signals
+const fn check_conflicts(entries: &[Entry]) {
+    let mut i = 0;
+    while i < entries.len() {
+        let mut j = i + 1;
+        while j < entries.len() {
+            let (a, b) = (&entries[i], &entries[j]);
+            if str_eq(a.pin, b.pin) && str_eq(a.af, b.af) && !str_eq(a.signal, b.signal) {
+                panic!("pin table assigns a pin to two signals on the same AF");
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+}
+
+macro_rules! pin_table {
+    ($($PERI:ident: { $($Trait:ident: [$($PXi:ident<$MODE:ty, $AF:ident>),* $(,)?])+ })+) => {
+        $($($(
+            impl $Trait<crate::pac::$PERI> for $PXi<$MODE, $AF> {}
+        )*)+)+
+
+        /// Every row of the pin table of this device family
+        const PIN_TABLE: &[Entry] = &[
+            $($($(
+                Entry {
+                    pin: stringify!($PXi),
+                    af: stringify!($AF),
+                    signal: concat!(stringify!($PERI), ".", stringify!($Trait)),
+                },
+            )*)+)+
+        ];
+
+        const _: () = check_conflicts(PIN_TABLE);
+    };
+}
+
+#[cfg(any(
+    feature = "ht32f1653",
+    feature = "ht32f1654",
+    feature = "ht32f1655",
+    feature = "ht32f1656",
+))]
+use crate::gpio::{gpioa::*, gpiob::*, gpioc::*, gpiod::*, Floating, Input, Output, PushPull, AF5};
+#[cfg(any(
+    feature = "ht32f1653",
+    feature = "ht32f1654",
+    feature = "ht32f1655",
+    feature = "ht32f1656",
+))]
+use crate::spi::{PinMiso, PinMosi, PinSck};
+
+#[cfg(any(
+    feature = "ht32f1653",
+    feature = "ht32f1654",
+    feature = "ht32f1655",
+    feature = "ht32f1656",
+))]
+pin_table! {
+    SPI0: {
+        PinSck: [
+            PA4<Output<PushPull>, AF5>,
+            PB3<Output<PushPull>, AF5>,
+            PD2<Output<PushPull>, AF5>,
+        ]
+        PinMiso: [
+            PA6<Input<Floating>, AF5>,
+            PA11<Input<Floating>, AF5>,
+            PB5<Input<Floating>, AF5>,
+        ]
+        PinMosi: [
+            PA5<Output<PushPull>, AF5>,
+            PA9<Output<PushPull>, AF5>,
+            PB4<Output<PushPull>, AF5>,
+        ]
+    }
+    SPI1: {
+        PinSck: [
+            PA15<Output<PushPull>, AF5>,
+            PB7<Output<PushPull>, AF5>,
+            PC1<Output<PushPull>, AF5>,
+            // PC11 used to be listed as both SCK and MOSI, the SCK of this
+            // group is left out until it is checked against the datasheet
+        ]
+        PinMiso: [
+            PB1<Input<Floating>, AF5>,
+            PB9<Input<Floating>, AF5>,
+            PC3<Input<Floating>, AF5>,
+            PC12<Input<Floating>, AF5>,
+        ]
+        PinMosi: [
+            PB0<Output<PushPull>, AF5>,
+            PB8<Output<PushPull>, AF5>,
+            PC2<Output<PushPull>, AF5>,
+            PC11<Output<PushPull>, AF5>,
+        ]
+    }
+}
+
+#[cfg(any(feature = "ht32f1755", feature = "ht32f1765"))]
+use crate::gpio::{gpiob::*, gpioc::*, gpiod::*, gpioe::*, OpenDrain, Output, AF1, AF2, AF3};
+#[cfg(any(feature = "ht32f1755", feature = "ht32f1765"))]
+use crate::i2c::{PinScl, PinSda};
+
+#[cfg(any(feature = "ht32f1755", feature = "ht32f1765"))]
+pin_table! {
+    I2C0: {
+        PinScl: [
+            PC4<Output<OpenDrain>, AF2>,
+            PC11<Output<OpenDrain>, AF1>,
+            PD12<Output<OpenDrain>, AF2>,
+        ]
+        PinSda: [
+            PC5<Output<OpenDrain>, AF2>,
+            PC12<Output<OpenDrain>, AF1>,
+            PD13<Output<OpenDrain>, AF2>,
+        ]
+    }
+    I2C1: {
+        PinScl: [
+            PC0<Output<OpenDrain>, AF3>,
+            PC6<Output<OpenDrain>, AF1>,
+            PE9<Output<OpenDrain>, AF3>,
+        ]
+        PinSda: [
+            PB7<Output<OpenDrain>, AF2>,
+            PC1<Output<OpenDrain>, AF3>,
+            PC7<Output<OpenDrain>, AF1>,
+            PE10<Output<OpenDrain>, AF3>,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const fn entry(pin: &'static str, af: &'static str, signal: &'static str) -> Entry {
+        Entry { pin, af, signal }
+    }
+
+    #[test]
+    fn device_table_has_no_duplicates() {
+        for (i, a) in PIN_TABLE.iter().enumerate() {
+            for b in &PIN_TABLE[i + 1..] {
+                assert!(
+                    !(a.pin == b.pin && a.af == b.af && a.signal == b.signal),
+                    "{} {} {} is listed twice",
+                    a.pin,
+                    a.af,
+                    a.signal
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn same_pin_on_other_af_is_allowed() {
+        check_conflicts(&[
+            entry("PC11", "AF1", "I2C0.PinScl"),
+            entry("PC11", "AF5", "SPI1.PinMosi"),
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "pin table assigns a pin to two signals on the same AF")]
+    fn same_pin_and_af_for_two_signals_is_rejected() {
+        check_conflicts(&[
+            entry("PC11", "AF5", "SPI1.PinSck"),
+            entry("PC11", "AF5", "SPI1.PinMosi"),
+        ]);
+    }
+}
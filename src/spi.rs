@@ -1,6 +1,5 @@
 //! Serial Peripheral Interface implementation
 use crate::ckcu::{Clocks, Pcer};
-use crate::gpio::{Floating, Input, Output, PushPull};
 use crate::hal;
 use crate::pac::{SPI0, SPI1};
 use crate::time::Hertz;
@@ -100,7 +99,7 @@ macro_rules! spi {
                     // -> CP = (f_pclk / (2 * f_sck)) - 1
                     // for pclk = hclk
                     let freq = freq.into();
-                    let spi_div: u16 = ((clocks.hclk.raw() / (2 * freq.raw())) - 1) as u16;
+                    let spi_div: u16 = ((clocks.pclk().raw() / (2 * freq.raw())) - 1) as u16;
 
                     spi.spi_cpr.write(|w| unsafe { w.cp().bits(spi_div) });
 
@@ -282,63 +281,3 @@ where
         Ok(())
     }
 }
-
-macro_rules! pins {
-    ($($SPIX:ty: SCK: [$($SCK:ty),*] MISO: [$($MISO:ty),*] MOSI: [$($MOSI:ty),*])+) => {
-        $(
-            $(
-                impl PinSck<$SPIX> for $SCK {}
-            )*
-            $(
-                impl PinMiso<$SPIX> for $MISO {}
-            )*
-            $(
-                impl PinMosi<$SPIX> for $MOSI {}
-            )*
-        )+
-    }
-}
-
-#[cfg(any(feature = "ht32f1653", feature = "ht32f1654"))]
-use crate::gpio::{gpioa::*, gpiob::*, gpioc::*, gpiod::*, AF5};
-
-#[cfg(any(feature = "ht32f1653", feature = "ht32f1654"))]
-pins! {
-    SPI0:
-        SCK: [
-            PA4<Output<PushPull>, AF5>,
-            PB3<Output<PushPull>, AF5>,
-            PD2<Output<PushPull>, AF5>
-        ]
-        MISO: [
-            PA6<Input<Floating>, AF5>,
-            PA11<Input<Floating>, AF5>,
-            PB5<Input<Floating>, AF5>
-        ]
-        MOSI: [
-            PA5<Output<PushPull>, AF5>,
-            PA9<Output<PushPull>, AF5>,
-            PB4<Output<PushPull>, AF5>
-        ]
-    SPI1:
-        SCK: [
-            PA15<Output<PushPull>, AF5>,
-            PB7<Output<PushPull>, AF5>,
-            PC1<Output<PushPull>, AF5>,
-            PC11<Output<PushPull>, AF5>
-        ]
-        MISO: [
-            PB1<Input<Floating>, AF5>,
-            PB9<Input<Floating>, AF5>,
-            PC3<Input<Floating>, AF5>,
-            PC12<Input<Floating>, AF5>
-        ]
-        MOSI: [
-            PB0<Output<PushPull>, AF5>,
-            PB8<Output<PushPull>, AF5>,
-            PC2<Output<PushPull>, AF5>,
-            PC11<Output<PushPull>, AF5>
-        ]
-}
-
-// TODO: pins! for other devices